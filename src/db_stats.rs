@@ -1,175 +1,4633 @@
+use std::collections::{HashMap, HashSet};
 use std::str::FromStr;
+use std::hash::{Hash, Hasher};
+use std::sync::Arc;
+
+use rusqlite::OptionalExtension;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Schema {
+    pub name: String,
+    pub summary: DatabaseSummary,
+    pub tables: Vec<Table>,
+    pub views: Vec<View>,
+    pub triggers: Vec<Trigger>
+}
+
+/// Amount of rows to measure per table instead of scanning whole tables.
+///
+/// Pages usage and amounts of rows are estimated from samples of the
+/// b-trees pages read from the database file, and columns statistics
+/// from samples of the table rows.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Sampling {
+    /// Fraction of the table rows from 0.0 to 1.0.
+    Fraction(f64),
+
+    /// Fixed amount of the table rows.
+    Rows(u64)
+}
+
+impl Sampling {
+    /// Amount of rows to sample from the table with given amount of rows.
+    pub fn sample_size(&self, rows: u64) -> u64 {
+        let size = match self {
+            Self::Fraction(fraction) => (rows as f64 * fraction).ceil() as u64,
+            Self::Rows(size) => *size
+        };
+
+        size.clamp(1, rows.max(1))
+    }
+
+    /// Amount of pages to read from a b-tree level with given amount
+    /// of pages. Sampled amount of rows doesn't tell how many pages
+    /// are needed so the minimal amount is used for it.
+    pub fn sample_pages(&self, pages: u64) -> u64 {
+        let size = match self {
+            Self::Fraction(fraction) => (pages as f64 * fraction).ceil() as u64,
+            Self::Rows(_) => 0
+        };
+
+        size.max(MIN_SAMPLED_PAGES).min(pages)
+    }
+}
+
+impl FromStr for Sampling {
+    type Err = anyhow::Error;
+
+    /// Parse either percent of rows like `1%` or amount of rows like `10000`.
+    fn from_str(sampling: &str) -> Result<Self, Self::Err> {
+        if let Some(percent) = sampling.strip_suffix('%') {
+            let percent = percent.trim().parse::<f64>()?;
+
+            if !(percent > 0.0 && percent <= 100.0) {
+                anyhow::bail!("Sample percent must be in (0, 100] range");
+            }
+
+            return Ok(Self::Fraction(percent / 100.0));
+        }
+
+        let rows = sampling.trim().parse::<u64>()?;
+
+        if rows == 0 {
+            anyhow::bail!("Sample must contain at least one row");
+        }
+
+        Ok(Self::Rows(rows))
+    }
+}
+
+/// Progress of the database analysis.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Hash)]
+pub struct Progress {
+    /// Schema being analyzed.
+    pub schema: String,
+
+    /// Amount of analyzed schemas and their total amount.
+    pub schemas_done: usize,
+    pub schemas: usize,
+
+    /// Amount of analyzed tables of the schema and their total amount.
+    pub tables_done: usize,
+    pub tables: usize,
+
+    /// Table being analyzed, or `None` if pages usage is being read.
+    pub table: Option<String>,
+
+    /// Amount of measured rows of the table and the amount of rows
+    /// expected to be measured.
+    pub rows_done: u64,
+    pub rows: u64
+}
+
+impl Progress {
+    /// Fraction of the analyzed database from 0.0 to 1.0.
+    pub fn fraction(&self) -> f64 {
+        if self.schemas == 0 {
+            return 0.0;
+        }
+
+        let table_fraction = if self.rows == 0 {
+            0.0
+        } else {
+            (self.rows_done as f64 / self.rows as f64).min(1.0)
+        };
+
+        let schema_fraction = if self.tables == 0 {
+            0.0
+        } else {
+            (self.tables_done as f64 + table_fraction) / self.tables as f64
+        };
+
+        (self.schemas_done as f64 + schema_fraction) / self.schemas as f64
+    }
+}
+
+/// Update reported while the database analysis is in progress.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Update {
+    Progress(Progress),
+
+    /// Schema with known pages usage and all the tables pending analysis.
+    Schema(Schema),
+
+    /// Analyzed table of the schema.
+    Table {
+        schema: String,
+        table: Table
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct DatabaseSummary {
+    /// Size of the database file on disk.
+    pub file_size: u64,
+
+    pub page_size: u64,
+    pub page_count: u64,
+    pub freelist_count: u64,
+
+    /// Amount of bytes used by the `sqlite_schema` table.
+    pub schema_size: u64,
+
+    /// Amount of bytes used by SQLite internal objects: `sqlite_schema`,
+    /// `sqlite_sequence`, `sqlite_stat*` tables and automatic indexes.
+    pub system_size: u64,
+
+    /// Amount of bytes used to store the cells content in all the pages.
+    pub payload: u64,
+
+    /// Amount of unused bytes in all the pages.
+    pub unused: u64,
+
+    /// Amount of bytes of the database file and its WAL-resident pages
+    /// which are not used by any table, index, internal object, or the freelist.
+    pub overhead: u64,
+
+    /// Amount of pages past the end of the database file which
+    /// are stored only in the `-wal` file until the checkpoint.
+    pub wal_pages: u64,
+
+    /// Pages locality of all the b-trees in the database.
+    pub locality: Locality,
+
+    /// Shared between the pending and the analyzed schema
+    /// as it has an entry per every fragment of the file.
+    pub page_map: Arc<PageMap>,
+
+    /// Amount of pages read to estimate pages usage of the b-trees,
+    /// or `None` if every page was read. The page map is empty then.
+    pub sampled_pages: Option<u64>,
+
+    /// Header of the database file, or `None` for in-memory databases.
+    pub header: Option<DatabaseHeader>,
+
+    pub journal: Journal
+}
+
+impl DatabaseSummary {
+    /// Amount of bytes used by the freelist pages.
+    #[inline]
+    pub fn free_size(&self) -> u64 {
+        self.freelist_count * self.page_size
+    }
+
+    /// Amount of bytes used on disk by the database and its journal files.
+    #[inline]
+    pub fn footprint(&self) -> u64 {
+        self.file_size + self.journal.size()
+    }
+
+    /// Whether the WAL file is so large compared to the database
+    /// that it should be checkpointed.
+    pub fn is_wal_oversized(&self) -> bool {
+        self.journal.wal_size.is_some_and(|wal_size| wal_size > self.file_size * OVERSIZED_WAL_RATIO)
+    }
+}
+
+/// Journal files stored next to the database file.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Hash)]
+pub struct Journal {
+    /// Size of the `-wal` file, or `None` if it doesn't exist.
+    pub wal_size: Option<u64>,
+
+    /// Amount of frames in the `-wal` file, including the frames
+    /// which were already checkpointed.
+    pub wal_frames: u64,
+
+    /// Size of the `-shm` WAL index file, or `None` if it doesn't exist.
+    pub shm_size: Option<u64>,
+
+    /// Size of the `-journal` rollback journal file, or `None` if it doesn't exist.
+    pub journal_size: Option<u64>
+}
+
+impl Journal {
+    /// Total size of all the journal files.
+    pub fn size(&self) -> u64 {
+        self.wal_size.unwrap_or_default() +
+            self.shm_size.unwrap_or_default() +
+            self.journal_size.unwrap_or_default()
+    }
+}
+
+/// The first 100 bytes of the database file.
+///
+/// See <https://www.sqlite.org/fileformat.html#the_database_header>.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Hash)]
+pub struct DatabaseHeader {
+    pub page_size: u64,
+
+    /// File format write version: 1 for rollback journal, 2 for WAL.
+    pub write_version: u8,
+
+    /// File format read version: 1 for rollback journal, 2 for WAL.
+    pub read_version: u8,
+
+    /// Amount of bytes reserved at the end of each page, e.g. by encryption extensions.
+    pub reserved_bytes: u8,
+
+    /// Incremented on every transaction which modifies the database.
+    pub change_counter: u32,
+
+    /// Size of the database in pages, valid only if `version_valid_for`
+    /// equals to `change_counter`.
+    pub page_count: u32,
+
+    pub freelist_trunk_page: u32,
+    pub freelist_count: u32,
+
+    /// Incremented every time the database schema changes.
+    pub schema_cookie: u32,
+
+    pub schema_format: u32,
+    pub default_cache_size: i32,
+
+    /// Largest root b-tree page when auto-vacuum is enabled, or 0 otherwise.
+    pub largest_root_page: u32,
+
+    /// 1 for UTF-8, 2 for UTF-16le and 3 for UTF-16be.
+    pub text_encoding: u32,
+
+    pub user_version: i32,
+    pub incremental_vacuum: bool,
+    pub application_id: i32,
+
+    /// Value of the change counter when `sqlite_version` was stored.
+    pub version_valid_for: u32,
+
+    /// `SQLITE_VERSION_NUMBER` of the library which last wrote the file.
+    pub sqlite_version: u32
+}
+
+impl DatabaseHeader {
+    pub const SIZE: usize = 100;
+
+    pub fn parse(header: &[u8; Self::SIZE]) -> anyhow::Result<Self> {
+        if !header.starts_with(b"SQLite format 3\0") {
+            anyhow::bail!("Invalid SQLite database header string");
+        }
+
+        let u32_at = |offset: usize| u32::from_be_bytes([header[offset], header[offset + 1], header[offset + 2], header[offset + 3]]);
+
+        // Page size of 65536 doesn't fit 2 bytes and is stored as 1.
+        let page_size = match u16::from_be_bytes([header[16], header[17]]) {
+            1 => 65536,
+            page_size => page_size as u64
+        };
+
+        Ok(Self {
+            page_size,
+            write_version: header[18],
+            read_version: header[19],
+            reserved_bytes: header[20],
+            change_counter: u32_at(24),
+            page_count: u32_at(28),
+            freelist_trunk_page: u32_at(32),
+            freelist_count: u32_at(36),
+            schema_cookie: u32_at(40),
+            schema_format: u32_at(44),
+            default_cache_size: u32_at(48) as i32,
+            largest_root_page: u32_at(52),
+            text_encoding: u32_at(56),
+            user_version: u32_at(60) as i32,
+            incremental_vacuum: u32_at(64) != 0,
+            application_id: u32_at(68) as i32,
+            version_valid_for: u32_at(92),
+            sqlite_version: u32_at(96)
+        })
+    }
+
+    /// Whether the database size in the header can be trusted.
+    ///
+    /// Files written by old SQLite versions have the size unset.
+    #[inline]
+    pub fn is_page_count_valid(&self) -> bool {
+        self.page_count > 0 && self.version_valid_for == self.change_counter
+    }
+
+    #[inline]
+    pub fn is_auto_vacuum(&self) -> bool {
+        self.largest_root_page > 0
+    }
+
+    /// Name of the text encoding.
+    pub fn encoding(&self) -> &'static str {
+        match self.text_encoding {
+            1 => "UTF-8",
+            2 => "UTF-16le",
+            3 => "UTF-16be",
+            _ => "unknown"
+        }
+    }
+
+    /// Version of the SQLite which last wrote the file in `X.Y.Z` format.
+    pub fn version(&self) -> String {
+        format!("{}.{}.{}", self.sqlite_version / 1_000_000, self.sqlite_version / 1000 % 1000, self.sqlite_version % 1000)
+    }
+}
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct Table {
     pub name: String,
     pub rows: u64,
+
+    /// Amount of rows the columns statistics were estimated from,
+    /// or `None` if all the rows were measured.
+    pub sampled_rows: Option<u64>,
+
+    /// Whether the sampled rows are not uniformly random because the
+    /// rowids are too sparse, so the margins are widened approximations.
+    pub is_sample_approximate: bool,
+
+    /// Amount of rows recorded in `sqlite_stat1` by the last `ANALYZE`,
+    /// or `None` if the table wasn't analyzed.
+    pub analyzed_rows: Option<u64>,
+
     pub size: u64,
+    pub payload: u64,
+    pub unused: u64,
+    pub btree: BTree,
+
+    /// Whether this is an SQLite internal table, e.g. `sqlite_sequence`.
+    pub is_system: bool,
+
+    /// Whether the table is still being analyzed and only its
+    /// pages usage and indexes are known.
+    pub is_pending: bool,
+
+    /// Module of the virtual table, or `None` for ordinary tables.
+    pub module: Option<String>,
+
     pub columns: Vec<Column>,
-    pub indexes: Vec<Index>
+    pub indexes: Vec<Index>,
+
+    /// Shadow tables storing content of the virtual table.
+    pub shadows: Vec<Table>
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+impl Table {
+    /// Whether the rows amount recorded by the last `ANALYZE` is so
+    /// different from the real one that the query planner is misled.
+    pub fn is_analysis_stale(&self) -> bool {
+        let Some(analyzed_rows) = self.analyzed_rows else {
+            return false;
+        };
+
+        if self.is_pending {
+            return false;
+        }
+
+        let difference = analyzed_rows.abs_diff(self.rows);
+
+        difference > STALE_ANALYSIS_ROWS &&
+            difference as f64 > analyzed_rows.max(self.rows) as f64 * STALE_ANALYSIS_FRACTION
+    }
+
+    /// Whether values of the column are stored in the records of the
+    /// table's b-tree, so their disk usage is measured. Virtual tables
+    /// keep values in the shadow tables or compute them in the module.
+    pub fn stores_column(&self, column: &Column) -> bool {
+        self.module.is_none() && column.kind.is_stored()
+    }
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq, Hash)]
 pub struct Column {
     pub name: String,
-    pub format: Format,
-    pub length: u64
+
+    /// Type of the column as written in the `CREATE TABLE` statement.
+    pub declared_type: String,
+
+    pub affinity: Affinity,
+    pub kind: ColumnKind,
+
+    /// Amount of bytes used by the column values in the records,
+    /// including serial type varints from the records headers.
+    pub size: u64,
+
+    /// Sum of `LENGTH()` of all the column values.
+    pub logical_size: u64,
+
+    /// Amount of bytes used by the column values in the records
+    /// which don't fit into a single page and spill into overflow pages.
+    pub overflow_size: u64,
+
+    /// Amount of NULL values.
+    pub nulls: u64,
+
+    /// Amount of distinct non-NULL values.
+    pub distinct: u64,
+
+    /// Whether `distinct` is estimated rather than exact.
+    pub is_distinct_estimated: bool,
+
+    /// Minimal amount of bytes used by a non-NULL value in the record body.
+    pub min_value_size: u64,
+
+    /// Maximal amount of bytes used by a non-NULL value in the record body.
+    pub max_value_size: u64,
+
+    /// Amount of bytes used by all non-NULL values in the record body.
+    pub values_size: u64,
+
+    /// Amount of rows and bytes of values of each storage class,
+    /// indexed by `StorageClass`.
+    pub storage: [StorageUsage; 5],
+
+    /// Margins of error of the statistics estimated from a sample
+    /// of rows, or `None` if they're exact.
+    pub margins: Option<Margins>
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
-pub struct Index {
-    pub name: String,
-    pub size: u64
+impl Column {
+    /// Amount of values with storage class not matching the column's affinity.
+    pub fn mismatched_rows(&self) -> u64 {
+        StorageClass::ALL.iter()
+            .filter(|class| !self.affinity.is_expected(**class))
+            .map(|class| self.storage[*class as usize].rows)
+            .sum()
+    }
+
+    /// Average amount of bytes used by a non-NULL value in the record body.
+    pub fn average_value_size(&self, rows: u64) -> f64 {
+        let values = rows.saturating_sub(self.nulls);
+
+        if values == 0 {
+            return 0.0;
+        }
+
+        self.values_size as f64 / values as f64
+    }
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
-pub enum Format {
+/// Storage class of a value as reported by the `typeof()` function.
+///
+/// https://sqlite.org/datatype3.html#storage_classes_and_datatypes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum StorageClass {
+    Null,
     Integer,
-    Numeric,
     Real,
-    Boolean,
     Text,
-    Blob,
-    Other(String)
+    Blob
+}
+
+impl StorageClass {
+    pub const ALL: [Self; 5] = [Self::Null, Self::Integer, Self::Real, Self::Text, Self::Blob];
+
+    pub fn of(value: rusqlite::types::ValueRef) -> Self {
+        use rusqlite::types::ValueRef;
+
+        match value {
+            ValueRef::Null       => Self::Null,
+            ValueRef::Integer(_) => Self::Integer,
+            ValueRef::Real(_)    => Self::Real,
+            ValueRef::Text(_)    => Self::Text,
+            ValueRef::Blob(_)    => Self::Blob
+        }
+    }
 }
 
-impl std::fmt::Display for Format {
+impl std::fmt::Display for StorageClass {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            Self::Integer  => f.write_str("integer"),
-            Self::Numeric  => f.write_str("numeric"),
-            Self::Real     => f.write_str("real"),
-            Self::Boolean  => f.write_str("boolean"),
-            Self::Text     => f.write_str("text"),
-            Self::Blob     => f.write_str("blob"),
-            Self::Other(s) => f.write_str(s)
+            Self::Null    => f.write_str("null"),
+            Self::Integer => f.write_str("integer"),
+            Self::Real    => f.write_str("real"),
+            Self::Text    => f.write_str("text"),
+            Self::Blob    => f.write_str("blob")
         }
     }
 }
 
-impl FromStr for Format {
-    type Err = anyhow::Error;
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct StorageUsage {
+    pub rows: u64,
 
-    fn from_str(format: &str) -> Result<Self, Self::Err> {
-        let format = format.to_ascii_lowercase();
-
-        // https://sqlite.org/datatype3.html
-        let formats = [
-            (Self::Integer, vec!["integer", "tinyint", "smallint", "mediumint", "bigint", "unsigned big int", "int2", "int8"]),
-            (Self::Numeric, vec!["numeric", "date"]),
-            (Self::Real,    vec!["real", "float", "double", "decimal"]),
-            (Self::Boolean, vec!["boolean"]),
-            (Self::Text,    vec!["text", "char", "varchar", "varying character", "nchar", "native character", "nvarchar", "longvarchar", "clob", "timestamp"]),
-            (Self::Blob,    vec!["blob"])
-        ];
+    /// Amount of bytes used by the values in the records.
+    pub size: u64
+}
 
-        for (name, definitions) in formats {
-            for definition in definitions {
-                if definition.starts_with(format.as_str()) || format.starts_with(definition) {
-                    return Ok(name);
-                }
-            }
+/// Half-widths of 95% confidence intervals of the estimated column statistics.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Margins {
+    pub size: u64,
+    pub logical_size: u64,
+    pub overflow_size: u64,
+    pub nulls: u64
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq, Hash)]
+pub struct BTree {
+    /// Amount of pages from the root to a leaf page.
+    pub depth: u64,
+
+    pub interior_pages: u64,
+    pub leaf_pages: u64,
+    pub overflow_pages: u64,
+
+    /// Amount of bytes used by the overflow pages.
+    pub overflow_size: u64,
+
+    /// Amount of cells stored in the interior pages.
+    pub interior_cells: u64,
+
+    /// Amount of cells stored in the leaf pages.
+    pub leaf_cells: u64,
+
+    /// Amount of bytes used by the leaf pages.
+    pub leaf_size: u64,
+
+    /// Amount of unused bytes in the leaf pages.
+    pub leaf_unused: u64,
+
+    /// Locality of the leaf and overflow pages in the database file.
+    pub locality: Locality
+}
+
+impl BTree {
+    /// Average amount of children of the interior pages.
+    pub fn fanout(&self) -> f64 {
+        if self.interior_pages == 0 {
+            return 0.0;
         }
 
-        Ok(Self::Other(format))
+        // Each interior page has a right-most child pointer besides its cells.
+        (self.interior_cells + self.interior_pages) as f64 / self.interior_pages as f64
     }
-}
 
-pub fn query_structure(connection: &rusqlite::Connection) -> anyhow::Result<Vec<Table>> {
-    let mut query = connection.prepare("
-        SELECT
-            sqlite_schema.name AS table_name,
-            SUM(dbstat.pgsize) AS bytes
-        FROM dbstat
-        JOIN sqlite_schema
-        ON dbstat.name = sqlite_schema.name
-        WHERE sqlite_schema.type = 'table'
-        GROUP BY sqlite_schema.name;
-    ")?;
+    /// Average amount of cells stored in a leaf page.
+    pub fn cells_per_leaf(&self) -> f64 {
+        if self.leaf_pages == 0 {
+            return 0.0;
+        }
 
-    let mut tables_raw = query.query_map([], |row| {
-        let table_name = row.get::<_, String>("table_name")?;
-        let bytes = row.get::<_, u64>("bytes")?;
+        self.leaf_cells as f64 / self.leaf_pages as f64
+    }
 
-        Ok((table_name, bytes))
-    })?.collect::<Result<Vec<_>, _>>()?;
+    /// Average fraction (`0.0..=1.0`) of used bytes in the leaf pages.
+    pub fn leaf_fill(&self) -> f64 {
+        if self.leaf_size == 0 {
+            return 0.0;
+        }
 
-    let mut tables = Vec::with_capacity(tables_raw.len());
+        1.0 - self.leaf_unused as f64 / self.leaf_size as f64
+    }
+}
+
+/// Owners of all the pages of the database file.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Hash)]
+pub struct PageMap {
+    /// Names of the tables and indexes owning the pages.
+    pub objects: Vec<String>,
+
+    /// Sequences of pages with the same owner, covering the whole file.
+    pub runs: Vec<PageRun>
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct PageRun {
+    /// Number of the first page of the sequence, starting from 1.
+    pub first_page: u64,
 
-    for (table, size) in tables_raw.drain(..) {
-        let rows = connection.prepare(&format!("SELECT COUNT(*) AS rows FROM `{table}`"))?
-            .query_row([], |row| row.get::<_, u64>("rows"))?;
+    /// Amount of pages in the sequence.
+    pub pages: u64,
 
-        let mut query = connection.prepare(&format!("SELECT name, type FROM pragma_table_info('{table}')"))?;
+    /// Index of the owning object in `PageMap::objects`, or `None` for
+    /// pages not used by any b-tree (freelist, pointer map, lock-byte page).
+    pub owner: Option<usize>
+}
 
-        let mut columns_raw = query.query_map([], |row| {
-            let name = row.get::<_, String>("name")?;
-            let format = row.get::<_, String>("type")?;
+/// Statistics of jumps between the pages of a b-tree, taken in the order
+/// in which they are read when scanning it from start to end.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Locality {
+    /// Total amount of jumps between pages.
+    pub jumps: u64,
 
-            Ok((name, format))
-        })?.map(|row| {
-            row.map_err(|err| anyhow::anyhow!(err))
-                .and_then(|(name, format)| Ok((name, Format::from_str(&format)?)))
-        }).collect::<Result<Vec<_>, _>>()?;
+    /// Amount of jumps to the next page in the file.
+    pub sequential_jumps: u64,
 
-        let mut columns = Vec::with_capacity(columns_raw.len());
+    /// Sum of distances (in pages) of all the jumps.
+    pub jumps_distance: u64
+}
 
-        for (column, format) in columns_raw.drain(..) {
-            let mut query = connection.prepare(&format!("
-                SELECT IFNULL(SUM(LENGTH(`{column}`)), 0) AS size
-                FROM `{table}` WHERE `{column}` IS NOT NULL
-            "))?;
+impl Locality {
+    /// Fraction (`0.0..=1.0`) of jumps to the next page in the file.
+    pub fn sequential(&self) -> f64 {
+        if self.jumps == 0 {
+            return 1.0;
+        }
 
-            let length = query.query_row([], |row| row.get::<_, u64>("size"))?;
+        self.sequential_jumps as f64 / self.jumps as f64
+    }
 
-            columns.push(Column {
-                name: column,
-                format,
-                length
-            });
+    /// Average distance (in pages) of a jump.
+    pub fn average_jump(&self) -> f64 {
+        if self.jumps == 0 {
+            return 0.0;
         }
 
-        columns.sort_by(|a, b| b.length.cmp(&a.length));
+        self.jumps_distance as f64 / self.jumps as f64
+    }
+}
+
+impl std::ops::AddAssign<&BTree> for BTree {
+    fn add_assign(&mut self, other: &BTree) {
+        self.depth = self.depth.max(other.depth);
+        self.interior_pages += other.interior_pages;
+        self.leaf_pages += other.leaf_pages;
+        self.overflow_pages += other.overflow_pages;
+        self.overflow_size += other.overflow_size;
+        self.interior_cells += other.interior_cells;
+        self.leaf_cells += other.leaf_cells;
+        self.leaf_size += other.leaf_size;
+        self.leaf_unused += other.leaf_unused;
+        self.locality += other.locality;
+    }
+}
+
+impl std::ops::AddAssign for Locality {
+    fn add_assign(&mut self, other: Self) {
+        self.jumps += other.jumps;
+        self.sequential_jumps += other.sequential_jumps;
+        self.jumps_distance += other.jumps_distance;
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Index {
+    pub name: String,
+    pub size: u64,
+    pub payload: u64,
+    pub unused: u64,
+    pub btree: BTree,
 
-        let mut query = connection.prepare(&format!("SELECT name FROM pragma_index_list('{table}')"))?;
+    /// Whether this index is created automatically by SQLite
+    /// for `UNIQUE` and `PRIMARY KEY` constraints.
+    pub is_system: bool,
 
-        let mut indexes_raw = query.query_map([], |row| row.get::<_, String>("name"))?
-            .collect::<Result<Vec<_>, _>>()?;
+    pub origin: IndexOrigin,
+    pub is_unique: bool,
+    pub is_partial: bool,
 
-        let mut indexes = Vec::with_capacity(indexes_raw.len());
+    /// `CREATE INDEX` statement of the index, or `None` for automatic indexes.
+    pub sql: Option<String>,
 
-        for index in indexes_raw.drain(..) {
-            let mut query = connection.prepare(&format!("
-                SELECT SUM(dbstat.pgsize) AS size FROM dbstat
-                JOIN sqlite_schema
-                ON dbstat.name = sqlite_schema.name
-                WHERE
-                    sqlite_schema.type = 'index' AND
-                    sqlite_schema.name = '{index}'
-                GROUP BY sqlite_schema.name;
-            "))?;
+    /// `WHERE` clause expression of the partial index.
+    pub condition: Option<String>,
 
-            // Some indexes can be empty.
-            if let Ok(size) = query.query_row([], |row| row.get::<_, u64>("size")) {
-                indexes.push(Index {
-                    name: index,
-                    size
-                });
-            }
+    /// Key columns of the index followed by the rowid
+    /// or the primary key columns of the table.
+    pub columns: Vec<IndexColumn>,
+
+    /// Another index or the rowid making this index unnecessary,
+    /// or `None` if the index isn't redundant.
+    pub redundancy: Option<Redundancy>,
+
+    /// Statistics gathered by the last `ANALYZE`, or `None`
+    /// if the index wasn't analyzed.
+    pub statistics: Option<IndexStatistics>
+}
+
+/// How the index was created.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum IndexOrigin {
+    /// `CREATE INDEX` statement.
+    #[default]
+    Create,
+
+    /// `UNIQUE` constraint.
+    Unique,
+
+    /// `PRIMARY KEY` constraint.
+    PrimaryKey
+}
+
+impl IndexOrigin {
+    fn from_code(code: &str) -> Self {
+        match code {
+            "u" => Self::Unique,
+            "pk" => Self::PrimaryKey,
+            _ => Self::Create
+        }
+    }
+
+    /// Code of the origin used by `pragma_index_list`.
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::Create => "c",
+            Self::Unique => "u",
+            Self::PrimaryKey => "pk"
         }
+    }
+}
 
-        indexes.sort_by(|a, b| b.size.cmp(&a.size));
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct IndexColumn {
+    /// Name of the table column, or `None` for the rowid and expressions.
+    pub name: Option<String>,
 
-        tables.push(Table {
-            name: table,
-            size,
-            rows,
-            columns,
-            indexes
-        });
+    /// Index of the table column, `-1` for the rowid and `-2` for expressions.
+    pub cid: i64,
+
+    /// Indexed expression as written in the `CREATE INDEX` statement.
+    pub expression: Option<String>,
+
+    /// Amount of bytes used by the column values in the index records,
+    /// including serial type varints from the records headers.
+    pub size: u64,
+
+    pub is_descending: bool,
+    pub collation: String,
+
+    /// Whether the column is a part of the index key,
+    /// and not the trailing rowid or primary key column.
+    pub is_key: bool
+}
+
+impl IndexColumn {
+    #[inline]
+    pub fn is_expression(&self) -> bool {
+        self.cid == -2
+    }
+
+    /// Whether both columns index the same values in the same order.
+    fn is_same(&self, other: &Self) -> bool {
+        !self.is_expression() &&
+        self.cid == other.cid &&
+        self.is_descending == other.is_descending &&
+        self.collation.eq_ignore_ascii_case(&other.collation)
     }
+}
+
+/// Index statistics stored in the `sqlite_stat1` and `sqlite_stat4` tables.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Hash)]
+pub struct IndexStatistics {
+    /// Amount of rows in the index.
+    pub rows: u64,
 
-    tables.sort_by(|a, b| b.size.cmp(&a.size));
+    /// Average amount of rows with the same values of the first
+    /// key column, of the first two key columns and so on.
+    pub rows_per_key: Vec<u64>,
 
-    Ok(tables)
+    /// Amount of index samples stored in `sqlite_stat4`.
+    pub samples: u64
+}
+
+/// Statistics gathered by `ANALYZE` for the tables and indexes of a schema.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Statistics {
+    /// Amount of rows per table.
+    pub tables: HashMap<String, u64>,
+
+    pub indexes: HashMap<String, IndexStatistics>
+}
+
+impl Statistics {
+    /// Set statistics of the table, its indexes and shadow tables.
+    pub fn apply(&self, table: &mut Table) {
+        table.analyzed_rows = self.tables.get(&table.name).copied();
+
+        for index in &mut table.indexes {
+            index.statistics = self.indexes.get(&index.name).cloned();
+        }
+
+        for shadow in &mut table.shadows {
+            self.apply(shadow);
+        }
+    }
+}
+
+/// Reason the index can be dropped without making any lookup slower.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Redundancy {
+    /// Index has the same key columns as the named index.
+    Duplicate(String),
+
+    /// Key columns of the index are a left prefix of the named index key.
+    Prefix(String),
+
+    /// Key columns of the index are a left prefix of the primary key which
+    /// is already the key of the table b-tree: the `INTEGER PRIMARY KEY`
+    /// rowid alias column, or the primary key of a `WITHOUT ROWID` table.
+    PrimaryKey
+}
+
+/// Indexes used by the query plans of a representative queries set.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Hash)]
+pub struct Workload {
+    pub queries: Vec<WorkloadQuery>
+}
+
+impl Workload {
+    /// Amount of the queries using the index of the schema.
+    pub fn uses(&self, schema: &str, index: &str) -> usize {
+        let is_index = |name: &String| {
+            name.strip_prefix(schema)
+                .and_then(|name| name.strip_prefix('.'))
+                .is_some_and(|name| name == index)
+        };
+
+        self.queries.iter()
+            .filter(|query| query.indexes.iter().any(is_index))
+            .count()
+    }
+
+    /// Whether the index can be dropped because none of the queries use it.
+    ///
+    /// Queries using an index redundant to this one are counted as its own
+    /// since they would use it once the redundant index is dropped.
+    /// Automatic indexes are never unused since they enforce constraints.
+    pub fn is_unused(&self, schema: &str, index: &Index, table_indexes: &[Index]) -> bool {
+        let is_replacement = |other: &Index| match &other.redundancy {
+            Some(Redundancy::Duplicate(name) | Redundancy::Prefix(name)) => name == &index.name,
+            _ => false
+        };
+
+        index.origin == IndexOrigin::Create && self.uses(schema, &index.name) == 0 &&
+            !table_indexes.iter().any(|other| is_replacement(other) && self.uses(schema, &other.name) > 0)
+    }
+
+    /// Amount of the queries which couldn't be planned.
+    pub fn failed(&self) -> usize {
+        self.queries.iter()
+            .filter(|query| query.error.is_some())
+            .count()
+    }
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq, Hash)]
+pub struct WorkloadQuery {
+    pub sql: String,
+
+    /// Names of the indexes used by the query plan,
+    /// qualified by their schemas as `schema.index`.
+    pub indexes: Vec<String>,
+
+    /// Error of the query planning, e.g. when it uses unknown table.
+    pub error: Option<String>
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct View {
+    pub name: String,
+    pub sql: String,
+
+    /// Tables and views read by the view, including
+    /// tables read through other views.
+    pub reads: Vec<String>
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Trigger {
+    pub name: String,
+
+    /// Table or view the trigger is attached to, qualified by the schema
+    /// name for temporary triggers attached to the tables of other schemas.
+    pub table: String,
+
+    /// Statement firing the trigger, or `None` if it couldn't be found.
+    pub event: Option<TriggerEvent>,
+
+    pub sql: String,
+
+    /// Tables read by the trigger statements, except the trigger's
+    /// table which is read for the `NEW` and `OLD` references.
+    pub reads: Vec<String>,
+
+    /// Tables modified by the trigger statements.
+    pub writes: Vec<String>
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TriggerEvent {
+    Insert,
+    Update,
+    Delete
+}
+
+impl std::fmt::Display for TriggerEvent {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Insert => write!(f, "INSERT"),
+            Self::Update => write!(f, "UPDATE"),
+            Self::Delete => write!(f, "DELETE")
+        }
+    }
+}
+
+/// Type affinity of the column.
+///
+/// https://sqlite.org/datatype3.html#type_affinity
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Affinity {
+    Text,
+    Numeric,
+    Integer,
+    Real,
+
+    #[default]
+    Blob,
+
+    /// `ANY` column of a `STRICT` table which stores values as is.
+    Any
+}
+
+impl std::fmt::Display for Affinity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Text    => f.write_str("text"),
+            Self::Numeric => f.write_str("numeric"),
+            Self::Integer => f.write_str("integer"),
+            Self::Real    => f.write_str("real"),
+            Self::Blob    => f.write_str("blob"),
+            Self::Any     => f.write_str("any")
+        }
+    }
+}
+
+impl Affinity {
+    /// Resolve affinity of the column from its declared type.
+    ///
+    /// https://sqlite.org/datatype3.html#determination_of_column_affinity
+    /// https://sqlite.org/stricttables.html
+    pub fn from_declared_type(declared_type: &str, is_strict: bool) -> Self {
+        let declared_type = declared_type.to_ascii_uppercase();
+
+        let contains = |patterns: &[&str]| {
+            patterns.iter().any(|pattern| declared_type.contains(pattern))
+        };
+
+        // In ordinary tables `ANY` type has numeric affinity.
+        if is_strict && declared_type == "ANY" {
+            Self::Any
+        }
+
+        else if contains(&["INT"]) {
+            Self::Integer
+        }
+
+        else if contains(&["CHAR", "CLOB", "TEXT"]) {
+            Self::Text
+        }
+
+        else if declared_type.is_empty() || contains(&["BLOB"]) {
+            Self::Blob
+        }
+
+        else if contains(&["REAL", "FLOA", "DOUB"]) {
+            Self::Real
+        }
+
+        else {
+            Self::Numeric
+        }
+    }
+
+    /// Check if values of the storage class are expected to be stored
+    /// in a column with this affinity.
+    pub fn is_expected(&self, class: StorageClass) -> bool {
+        matches!(
+            (self, class),
+            (_, StorageClass::Null) |
+            (Self::Blob | Self::Any, _) |
+            (Self::Text, StorageClass::Text) |
+            (Self::Real, StorageClass::Real) |
+            (Self::Integer | Self::Numeric, StorageClass::Integer | StorageClass::Real)
+        )
+    }
+}
+
+/// Kind of the column as reported by `pragma_table_xinfo`.
+///
+/// https://sqlite.org/pragma.html#pragma_table_xinfo
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ColumnKind {
+    #[default]
+    Ordinary,
+
+    /// Hidden column of a virtual table.
+    Hidden,
+
+    /// Generated column which is computed when read and not stored.
+    Virtual,
+
+    /// Generated column which is stored in the records.
+    Stored
+}
+
+impl ColumnKind {
+    fn from_hidden(hidden: u8) -> Self {
+        match hidden {
+            1 => Self::Hidden,
+            2 => Self::Virtual,
+            3 => Self::Stored,
+
+            _ => Self::Ordinary
+        }
+    }
+
+    /// Whether values of the column are stored in the table records.
+    pub fn is_stored(&self) -> bool {
+        matches!(self, Self::Ordinary | Self::Stored)
+    }
+}
+
+/// Amount of bytes needed to store the number as a varint.
+///
+/// https://sqlite.org/fileformat2.html#varint
+pub fn varint_size(mut value: u64) -> u64 {
+    // 9th byte stores full 8 bits.
+    if value >> 56 != 0 {
+        return 9;
+    }
+
+    let mut size = 1;
+
+    while value >= 0x80 {
+        value >>= 7;
+        size += 1;
+    }
+
+    size
+}
+
+/// Amount of bytes used to store the integer in the record body.
+///
+/// https://sqlite.org/fileformat2.html#record_format
+pub fn integer_size(value: i64) -> u64 {
+    match value {
+        0 | 1 => 0,
+
+        -0x80..=0x7F => 1,
+        -0x8000..=0x7FFF => 2,
+        -0x800000..=0x7FFFFF => 3,
+        -0x80000000..=0x7FFFFFFF => 4,
+        -0x800000000000..=0x7FFFFFFFFFFF => 6,
+
+        _ => 8
+    }
+}
+
+/// Amount of bytes used by the value in the record: serial type varint
+/// in the record header and the encoded value in the record body.
+///
+/// Text values are measured in UTF-16 if `is_utf16` is set. Integral
+/// floats are stored as integers if `is_real` (column affinity) is set.
+///
+/// https://sqlite.org/fileformat2.html#record_format
+pub fn value_size(value: rusqlite::types::ValueRef, is_utf16: bool, is_real: bool) -> (u64, u64) {
+    use rusqlite::types::ValueRef;
+
+    match value {
+        ValueRef::Null => (1, 0),
+        ValueRef::Integer(value) => (1, integer_size(value)),
+
+        ValueRef::Real(value) if is_real && value.fract() == 0.0 && value.abs() < i64::MAX as f64 => {
+            (1, integer_size(value as i64))
+        }
+
+        ValueRef::Real(_) => (1, 8),
+
+        ValueRef::Text(text) => {
+            let length = match std::str::from_utf8(text) {
+                Ok(text) if is_utf16 => text.encode_utf16().count() as u64 * 2,
+                _ => text.len() as u64
+            };
+
+            (varint_size(length * 2 + 13), length)
+        }
+
+        ValueRef::Blob(blob) => {
+            let length = blob.len() as u64;
+
+            (varint_size(length * 2 + 12), length)
+        }
+    }
+}
+
+/// Amount of distinct values counted exactly before switching
+/// to the HyperLogLog estimation. Every column of the measured
+/// table has its own counter so it must stay small.
+const EXACT_DISTINCT_LIMIT: usize = 1_000;
+
+/// Amount of bits of the value hash used to select HyperLogLog register.
+/// 4 KiB of registers give about 1.6% standard error.
+const HYPERLOGLOG_PRECISION: u32 = 12;
+
+/// Hasher of the map keys which are already hashes.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+struct IdentityHasher(u64);
+
+impl Hasher for IdentityHasher {
+    #[inline]
+    fn finish(&self) -> u64 {
+        self.0
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 = (self.0 << 8) | *byte as u64;
+        }
+    }
+
+    #[inline]
+    fn write_u64(&mut self, value: u64) {
+        self.0 = value;
+    }
+}
+
+/// Counter of distinct values. It's exact for up to `EXACT_DISTINCT_LIMIT`
+/// values and uses HyperLogLog to estimate larger amounts.
+///
+/// https://en.wikipedia.org/wiki/HyperLogLog
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DistinctCounter {
+    /// Amount of occurrences of each value hash.
+    hashes: Option<HashMap<u64, u64, std::hash::BuildHasherDefault<IdentityHasher>>>,
+
+    /// HyperLogLog registers, allocated once the exact counting is dropped.
+    registers: Vec<u8>
+}
+
+impl Default for DistinctCounter {
+    fn default() -> Self {
+        Self {
+            hashes: Some(HashMap::default()),
+            registers: Vec::new()
+        }
+    }
+}
+
+impl DistinctCounter {
+    /// Count non-NULL value. Integral floats are equal to integers
+    /// the same way as in SQLite.
+    pub fn insert(&mut self, value: rusqlite::types::ValueRef) {
+        use rusqlite::types::ValueRef;
+
+        let mut hasher = std::hash::DefaultHasher::new();
+
+        match value {
+            ValueRef::Null => return,
+
+            ValueRef::Integer(value) => (0, value).hash(&mut hasher),
+
+            ValueRef::Real(value) if value.fract() == 0.0 && value.abs() < i64::MAX as f64 => {
+                (0, value as i64).hash(&mut hasher);
+            }
+
+            ValueRef::Real(value) => (1, value.to_bits()).hash(&mut hasher),
+            ValueRef::Text(text) => (2, text).hash(&mut hasher),
+            ValueRef::Blob(blob) => (3, blob).hash(&mut hasher)
+        }
+
+        let hash = hasher.finish();
+
+        let Some(hashes) = &mut self.hashes else {
+            self.insert_hash(hash);
+
+            return;
+        };
+
+        *hashes.entry(hash).or_default() += 1;
+
+        if hashes.len() > EXACT_DISTINCT_LIMIT {
+            let hashes = self.hashes.take().unwrap_or_default();
+
+            self.registers = vec![0; 1 << HYPERLOGLOG_PRECISION];
+
+            for hash in hashes.into_keys() {
+                self.insert_hash(hash);
+            }
+        }
+    }
+
+    /// Update HyperLogLog register of the value hash.
+    fn insert_hash(&mut self, hash: u64) {
+        let register = (hash >> (64 - HYPERLOGLOG_PRECISION)) as usize;
+        let rank = (hash << HYPERLOGLOG_PRECISION).leading_zeros().min(64 - HYPERLOGLOG_PRECISION) as u8 + 1;
+
+        self.registers[register] = self.registers[register].max(rank);
+    }
+
+    /// Get amount of distinct values and whether it's estimated.
+    pub fn count(&self) -> (u64, bool) {
+        if let Some(hashes) = &self.hashes {
+            return (hashes.len() as u64, false);
+        }
+
+        let m = self.registers.len() as f64;
+
+        let sum = self.registers.iter()
+            .map(|register| 2.0_f64.powi(-(*register as i32)))
+            .sum::<f64>();
+
+        let mut estimate = 0.7213 / (1.0 + 1.079 / m) * m * m / sum;
+
+        let zeros = self.registers.iter()
+            .filter(|register| **register == 0)
+            .count();
+
+        // Small range correction.
+        if estimate <= 2.5 * m && zeros > 0 {
+            estimate = m * (m / zeros as f64).ln();
+        }
+
+        (estimate.round() as u64, true)
+    }
+
+    /// Get amount of values which occurred exactly once,
+    /// or `None` if values are not counted exactly.
+    pub fn singletons(&self) -> Option<u64> {
+        let hashes = self.hashes.as_ref()?;
+
+        Some(hashes.values().filter(|count| **count == 1).count() as u64)
+    }
+}
+
+/// Maximal amount of bytes of the cell payload which can be stored
+/// in a b-tree page before spilling into overflow pages.
+///
+/// https://sqlite.org/fileformat2.html#b_tree_pages
+pub fn max_local_payload(page_size: u64, is_index: bool) -> u64 {
+    if is_index {
+        (page_size - 12) * 64 / 255 - 23
+    } else {
+        page_size - 35
+    }
+}
+
+/// Check if the table is created `WITHOUT ROWID` and if it is `STRICT`.
+fn query_table_options(connection: &rusqlite::Connection, schema: &str, table: &str) -> anyhow::Result<(bool, bool)> {
+    let options = connection.prepare(&format!("SELECT wr, strict FROM pragma_table_list('{table}') WHERE schema = '{schema}'"))?
+        .query_row([], |row| Ok((row.get::<_, bool>("wr")?, row.get::<_, bool>("strict")?)))?;
+
+    Ok(options)
+}
+
+/// Get columns of the table, including hidden and generated ones,
+/// with empty statistics.
+fn query_columns(connection: &rusqlite::Connection, schema: &str, table: &str, is_strict: bool) -> anyhow::Result<Vec<Column>> {
+    let mut query = connection.prepare(&format!("SELECT name, type, hidden FROM pragma_table_xinfo('{table}', '{schema}')"))?;
+
+    let columns = query.query_map([], |row| {
+        let name = row.get::<_, String>("name")?;
+        let declared_type = row.get::<_, String>("type")?;
+        let hidden = row.get::<_, u8>("hidden")?;
+
+        Ok(Column {
+            name,
+            affinity: Affinity::from_declared_type(&declared_type, is_strict),
+            declared_type,
+            kind: ColumnKind::from_hidden(hidden),
+            ..Column::default()
+        })
+    })?.collect::<Result<Vec<_>, _>>()?;
+
+    Ok(columns)
+}
+
+/// Get name of the column which is an alias for the table's rowid.
+///
+/// https://sqlite.org/lang_createtable.html#rowid
+fn query_rowid_alias(connection: &rusqlite::Connection, schema: &str, table: &str) -> anyhow::Result<Option<String>> {
+    // Primary keys which aren't the rowid alias have an automatic index,
+    // including WITHOUT ROWID ones and `INTEGER PRIMARY KEY DESC`.
+    let has_primary_key_index = connection.query_row(
+        &format!("SELECT COUNT(*) FROM pragma_index_list('{table}', '{schema}') WHERE origin = 'pk'"),
+        [],
+        |row| row.get::<_, bool>(0)
+    )?;
+
+    if has_primary_key_index {
+        return Ok(None);
+    }
+
+    let mut query = connection.prepare(&format!("SELECT name, type FROM pragma_table_info('{table}', '{schema}') WHERE pk > 0"))?;
+
+    let primary_key = query.query_map([], |row| {
+        let name = row.get::<_, String>("name")?;
+        let format = row.get::<_, String>("type")?;
+
+        Ok((name, format))
+    })?.collect::<Result<Vec<_>, _>>()?;
+
+    match primary_key.as_slice() {
+        [(name, format)] if format.eq_ignore_ascii_case("integer") => Ok(Some(name.clone())),
+
+        _ => Ok(None)
+    }
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq, Hash)]
+struct Usage {
+    size: u64,
+    payload: u64,
+    unused: u64,
+    btree: BTree
+}
+
+impl Usage {
+    /// Usage of an empty b-tree consisting of a single leaf root page.
+    fn empty(page_size: u64) -> Self {
+        // Leaf page header is 8 bytes long.
+        let unused = page_size.saturating_sub(8);
+
+        Self {
+            size: page_size,
+            payload: 0,
+            unused,
+            btree: BTree {
+                depth: 1,
+                leaf_pages: 1,
+                leaf_size: page_size,
+                leaf_unused: unused,
+                ..BTree::default()
+            }
+        }
+    }
+}
+
+/// Query pages usage of all the tables and indexes from the `dbstat` table.
+fn query_usage(connection: &rusqlite::Connection, schema: &str, page_count: u64) -> anyhow::Result<(HashMap<String, Usage>, PageMap)> {
+    // dbstat returns pages in this order without sorting them.
+    let mut query = connection.prepare("
+        SELECT name, path, pageno, pagetype, ncell, payload, unused, pgsize
+        FROM dbstat(?1)
+        ORDER BY name, path
+    ")?;
+
+    let mut rows = query.query([schema])?;
+
+    let mut objects = Vec::<String>::new();
+    let mut objects_usage = Vec::<Usage>::new();
+
+    // Sequences of pages in the order they are read, merged once sorted.
+    let mut owned_runs = Vec::<PageRun>::new();
+
+    // Last leaf or overflow page of the current object.
+    let mut prev_page = None;
+
+    while let Some(row) = rows.next()? {
+        let name = row.get_ref("name")?.as_str()?;
+
+        if objects.last().map(String::as_str) != Some(name) {
+            objects.push(name.to_string());
+            objects_usage.push(Usage::default());
+
+            prev_page = None;
+        }
+
+        let path = row.get_ref("path")?.as_str()?;
+        let page = row.get::<_, u64>("pageno")?;
+        let page_type = row.get_ref("pagetype")?.as_str()?;
+        let cells = row.get::<_, u64>("ncell")?;
+        let payload = row.get::<_, u64>("payload")?;
+        let unused = row.get::<_, u64>("unused")?;
+        let size = row.get::<_, u64>("pgsize")?;
+
+        let owner = Some(objects.len() - 1);
+
+        match owned_runs.last_mut() {
+            Some(run) if run.owner == owner && run.first_page + run.pages == page => run.pages += 1,
+
+            _ => owned_runs.push(PageRun {
+                first_page: page,
+                pages: 1,
+                owner
+            })
+        }
+
+        let usage = &mut objects_usage[objects.len() - 1];
+
+        usage.size += size;
+        usage.payload += payload;
+        usage.unused += unused;
+
+        let btree = &mut usage.btree;
+
+        match page_type {
+            "internal" => {
+                btree.interior_pages += 1;
+                btree.interior_cells += cells;
+            }
+
+            "leaf" => {
+                btree.leaf_pages += 1;
+                btree.leaf_cells += cells;
+                btree.leaf_size += size;
+                btree.leaf_unused += unused;
+            }
+
+            "overflow" => {
+                btree.overflow_pages += 1;
+                btree.overflow_size += size;
+            }
+
+            _ => ()
+        }
+
+        if page_type != "overflow" {
+            btree.depth = btree.depth.max(path.matches('/').count() as u64);
+        }
+
+        // Interior pages are not read in order during the scan.
+        if page_type != "internal" {
+            if let Some(prev_page) = prev_page {
+                let locality = &mut btree.locality;
+
+                locality.jumps += 1;
+                locality.jumps_distance += page.abs_diff(prev_page);
+
+                if page == prev_page + 1 {
+                    locality.sequential_jumps += 1;
+                }
+            }
+
+            prev_page = Some(page);
+        }
+    }
+
+    owned_runs.sort_unstable_by_key(|run| run.first_page);
+
+    let mut runs = Vec::<PageRun>::with_capacity(owned_runs.len());
+
+    // Pages between the owned sequences are not used by any b-tree.
+    let unowned_run = |first_page: u64, last_page: u64| PageRun {
+        first_page,
+        pages: last_page + 1 - first_page,
+        owner: None
+    };
+
+    for run in owned_runs {
+        let next_page = runs.last().map(|run| run.first_page + run.pages).unwrap_or(1);
+
+        if run.first_page > next_page {
+            runs.push(unowned_run(next_page, run.first_page - 1));
+        }
+
+        match runs.last_mut() {
+            Some(last) if last.owner == run.owner && last.first_page + last.pages == run.first_page => last.pages += run.pages,
+
+            _ => runs.push(run)
+        }
+    }
+
+    let next_page = runs.last().map(|run| run.first_page + run.pages).unwrap_or(1);
+
+    if page_count >= next_page {
+        runs.push(unowned_run(next_page, page_count));
+    }
+
+    let usage = objects.iter()
+        .cloned()
+        .zip(objects_usage)
+        .collect::<HashMap<_, _>>();
+
+    Ok((usage, PageMap { objects, runs }))
+}
+
+/// Generator of pseudo-random numbers seeded by SQLite.
+///
+/// https://prng.di.unimi.it/splitmix64.c
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Random(u64);
+
+impl Random {
+    fn new(connection: &rusqlite::Connection) -> anyhow::Result<Self> {
+        let seed = connection.query_row("SELECT random()", [], |row| row.get::<_, i64>(0))?;
+
+        Ok(Self(seed as u64))
+    }
+
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E3779B97F4A7C15);
+
+        let mut value = self.0;
+
+        value = (value ^ (value >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+        value = (value ^ (value >> 27)).wrapping_mul(0x94D049BB133111EB);
+
+        value ^ (value >> 31)
+    }
+
+    /// Get number from `0..bound` range.
+    fn below(&mut self, bound: u64) -> u64 {
+        ((self.next() as u128 * bound as u128) >> 64) as u64
+    }
+}
+
+/// Read a varint and return its value and size.
+///
+/// https://sqlite.org/fileformat2.html#varint
+fn read_varint(data: &[u8]) -> anyhow::Result<(u64, usize)> {
+    let mut value = 0;
+
+    for (i, byte) in data.iter().take(9).enumerate() {
+        // 9th byte stores full 8 bits.
+        if i == 8 {
+            return Ok(((value << 8) | *byte as u64, 9));
+        }
+
+        value = (value << 7) | (*byte & 0x7F) as u64;
+
+        if byte & 0x80 == 0 {
+            return Ok((value, i + 1));
+        }
+    }
+
+    anyhow::bail!("Varint is truncated")
+}
+
+/// Usage of a b-tree page decoded the same way as by the `dbstat` table,
+/// including the overflow pages of its cells.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+struct PageUsage {
+    is_leaf: bool,
+    cells: u64,
+
+    /// Local payload of the cells and the payload of their overflow pages.
+    payload: u64,
+
+    /// Unused bytes of the page and of the last overflow pages of its cells.
+    unused: u64,
+    page_unused: u64,
+
+    overflow_pages: u64,
+
+    /// Child pages including the right-most one, empty for leaf pages.
+    children: Vec<u64>
+}
+
+impl PageUsage {
+    /// Decode the b-tree page with given number. The first page
+    /// of the database file starts with the database header.
+    ///
+    /// https://sqlite.org/fileformat2.html#b_tree_pages
+    fn decode(data: &[u8], page: u64, usable_size: u64) -> anyhow::Result<Self> {
+        let corrupted = || anyhow::anyhow!("Page {page} is not a valid b-tree page");
+
+        let u16_at = |offset: usize| -> anyhow::Result<usize> {
+            let bytes = data.get(offset..offset + 2).ok_or_else(corrupted)?;
+
+            Ok(u16::from_be_bytes([bytes[0], bytes[1]]) as usize)
+        };
+
+        let u32_at = |offset: usize| -> anyhow::Result<u64> {
+            let bytes = data.get(offset..offset + 4).ok_or_else(corrupted)?;
+
+            Ok(u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as u64)
+        };
+
+        let header = if page == 1 { DatabaseHeader::SIZE } else { 0 };
+
+        let flags = *data.get(header).ok_or_else(corrupted)?;
+
+        let (is_leaf, header_size) = match flags {
+            0x0A | 0x0D => (true, 8),
+            0x02 | 0x05 => (false, 12),
+
+            _ => return Err(corrupted())
+        };
+
+        let cells = u16_at(header + 3)?;
+
+        // Content area offset of 0 means 65536.
+        let content = match u16_at(header + 5)? {
+            0 => 65536,
+            content => content
+        };
+
+        let mut page_unused = content as i64 - (header + header_size) as i64 - 2 * cells as i64;
+
+        page_unused += *data.get(header + 7).ok_or_else(corrupted)? as i64;
+
+        // Freeblocks are chained by their offsets.
+        let mut freeblock = u16_at(header + 1)?;
+
+        while freeblock != 0 {
+            page_unused += u16_at(freeblock + 2)? as i64;
+
+            let next = u16_at(freeblock)?;
+
+            if next != 0 && next < freeblock + 4 {
+                return Err(corrupted());
+            }
+
+            freeblock = next;
+        }
+
+        let mut usage = Self {
+            is_leaf,
+            cells: cells as u64,
+            page_unused: page_unused.max(0) as u64,
+            ..Self::default()
+        };
+
+        usage.unused = usage.page_unused;
+
+        let usable_size = usable_size as i64;
+
+        for i in 0..cells {
+            let mut offset = u16_at(header + header_size + i * 2)?;
+
+            if !is_leaf {
+                usage.children.push(u32_at(offset)?);
+
+                offset += 4;
+            }
+
+            // Table interior cells have no payload.
+            if flags == 0x05 {
+                continue;
+            }
+
+            let (payload, _) = read_varint(data.get(offset..).ok_or_else(corrupted)?)?;
+
+            let payload = payload as i64;
+
+            let min_local = (usable_size - 12) * 32 / 255 - 23;
+
+            let max_local = if flags == 0x0D {
+                usable_size - 35
+            } else {
+                (usable_size - 12) * 64 / 255 - 23
+            };
+
+            let mut local = min_local + (payload - min_local) % (usable_size - 4);
+
+            if local > max_local {
+                local = min_local;
+            }
+
+            usage.payload += local as u64;
+
+            // Overflow pages store the rest of the payload after
+            // a 4 bytes pointer to the next overflow page.
+            if payload > local {
+                let overflow = payload - local;
+                let overflow_pages = (overflow + usable_size - 5) / (usable_size - 4);
+                let last_payload = overflow - (overflow_pages - 1) * (usable_size - 4);
+
+                usage.overflow_pages += overflow_pages as u64;
+                usage.payload += overflow as u64;
+                usage.unused += (usable_size - 4 - last_payload) as u64;
+            }
+        }
+
+        if !is_leaf {
+            usage.children.push(u32_at(header + 8)?);
+        }
+
+        Ok(usage)
+    }
+}
+
+/// Estimate pages usage of the b-tree from a sample of its pages.
+///
+/// Pages of each level of the b-tree are sampled uniformly from the
+/// children of the sampled pages of the level above, and their usage
+/// is scaled by the inverse probability of reading them. Levels with
+/// no more pages than the sample size are read completely, so small
+/// b-trees are measured exactly.
+///
+/// Returns the estimated usage and the amount of read pages.
+fn estimate_usage(
+    file: &std::fs::File,
+    root: u64,
+    page_size: u64,
+    usable_size: u64,
+    sampling: Sampling,
+    random: &mut Random
+) -> anyhow::Result<(Usage, u64)> {
+    use std::io::{Read, Seek, SeekFrom};
+
+    let mut file = file;
+    let mut data = vec![0; page_size as usize];
+
+    let mut read_page = |page: u64| -> anyhow::Result<PageUsage> {
+        if page == 0 {
+            anyhow::bail!("B-tree refers to the page 0");
+        }
+
+        file.seek(SeekFrom::Start((page - 1) * page_size))?;
+        file.read_exact(&mut data)?;
+
+        PageUsage::decode(&data, page, usable_size)
+    };
+
+    // Sums of the pages statistics multiplied by the pages weights.
+    let mut interior_pages = 0.0;
+    let mut interior_cells = 0.0;
+    let mut leaf_pages = 0.0;
+    let mut leaf_cells = 0.0;
+    let mut leaf_unused = 0.0;
+    let mut overflow_pages = 0.0;
+    let mut payload = 0.0;
+    let mut unused = 0.0;
+
+    // Jumps between the sampled leaf pages and their next siblings.
+    let mut jumps = 0.0;
+    let mut sequential_jumps = 0.0;
+    let mut jumps_distance = 0.0;
+
+    let mut depth = 0;
+    let mut read_pages = 0;
+
+    // Sampled pages of the current level with their weights
+    // and their next siblings.
+    let mut level = vec![(root, 1.0, None::<u64>)];
+
+    while !level.is_empty() {
+        depth += 1;
+
+        // The same limit is used by the `dbstat` table.
+        if depth > 32 {
+            anyhow::bail!("B-tree with root page {root} is too deep");
+        }
+
+        let mut children = 0;
+        let mut level_pages = 0.0;
+
+        for (page, weight, next_page) in &level {
+            let usage = read_page(*page)?;
+
+            read_pages += 1;
+
+            if usage.is_leaf {
+                leaf_pages += weight;
+                leaf_cells += weight * usage.cells as f64;
+                leaf_unused += weight * usage.page_unused as f64;
+
+                if let Some(next_page) = next_page {
+                    jumps += weight;
+                    jumps_distance += weight * next_page.abs_diff(*page) as f64;
+
+                    if *next_page == page + 1 {
+                        sequential_jumps += weight;
+                    }
+                }
+            } else {
+                interior_pages += weight;
+                interior_cells += weight * usage.cells as f64;
+            }
+
+            overflow_pages += weight * usage.overflow_pages as f64;
+            payload += weight * usage.payload as f64;
+            unused += weight * usage.unused as f64;
+
+            children += usage.children.len() as u64;
+            level_pages += weight * usage.children.len() as f64;
+        }
+
+        if children == 0 {
+            break;
+        }
+
+        let sample_size = sampling.sample_pages(level_pages.round() as u64)
+            .clamp(1, children);
+
+        // Floyd's algorithm of sampling without replacement.
+        let mut sampled = HashSet::with_capacity(sample_size as usize);
+
+        for i in children - sample_size..children {
+            let child = random.below(i + 1);
+
+            if !sampled.insert(child) {
+                sampled.insert(i);
+            }
+        }
+
+        let mut sampled = sampled.into_iter().collect::<Vec<_>>();
+
+        sampled.sort_unstable();
+
+        let scale = children as f64 / sample_size as f64;
+
+        // Children are read again from the pages of the current level
+        // so only the sampled ones are kept.
+        let mut next_level = Vec::with_capacity(sampled.len());
+        let mut sampled = sampled.into_iter().peekable();
+        let mut first_child = 0;
+
+        for (page, weight, _) in &level {
+            let Some(&child) = sampled.peek() else {
+                break;
+            };
+
+            let usage = read_page(*page)?;
+            let last_child = first_child + usage.children.len() as u64;
+
+            if child < last_child {
+                while let Some(child) = sampled.next_if(|child| *child < last_child) {
+                    let i = (child - first_child) as usize;
+
+                    next_level.push((usage.children[i], weight * scale, usage.children.get(i + 1).copied()));
+                }
+            }
+
+            first_child = last_child;
+        }
+
+        level = next_level;
+    }
+
+    let interior_pages = interior_pages.round() as u64;
+    let leaf_pages = leaf_pages.round() as u64;
+    let overflow_pages = overflow_pages.round() as u64;
+
+    // Only the jumps between siblings are sampled, so their
+    // statistics are spread over all the jumps.
+    let all_jumps = (leaf_pages + overflow_pages).saturating_sub(1);
+
+    let locality = if jumps > 0.0 {
+        Locality {
+            jumps: all_jumps,
+            sequential_jumps: (all_jumps as f64 * sequential_jumps / jumps).round() as u64,
+            jumps_distance: (all_jumps as f64 * jumps_distance / jumps).round() as u64
+        }
+    } else {
+        Locality::default()
+    };
+
+    let usage = Usage {
+        size: (interior_pages + leaf_pages + overflow_pages) * page_size,
+        payload: payload.round() as u64,
+        unused: unused.round() as u64,
+        btree: BTree {
+            depth,
+            interior_pages,
+            leaf_pages,
+            overflow_pages,
+            overflow_size: overflow_pages * page_size,
+            interior_cells: interior_cells.round() as u64,
+            leaf_cells: leaf_cells.round() as u64,
+            leaf_size: leaf_pages * page_size,
+            leaf_unused: leaf_unused.round() as u64,
+            locality
+        }
+    };
+
+    Ok((usage, read_pages))
+}
+
+/// Estimate pages usage of all the tables and indexes from samples
+/// of their pages read from the database file instead of reading
+/// every page by the `dbstat` table.
+///
+/// Returns the usage and the amount of read pages, or `None` if the
+/// database file doesn't contain all the pages, e.g. when the database
+/// is stored in memory or some pages are in the WAL.
+fn query_sampled_usage(
+    connection: &rusqlite::Connection,
+    schema: &str,
+    sampling: Sampling,
+    page_size: u64,
+    page_count: u64,
+    header: Option<&DatabaseHeader>,
+    journal: &Journal
+) -> anyhow::Result<Option<(HashMap<String, Usage>, u64)>> {
+    let (Some(path), Some(header)) = (query_path(connection, schema)?, header) else {
+        return Ok(None);
+    };
+
+    if journal.wal_frames > 0 || std::fs::metadata(&path)?.len() < page_size * page_count {
+        return Ok(None);
+    }
+
+    let file = std::fs::File::open(path)?;
+
+    let usable_size = page_size - header.reserved_bytes as u64;
+
+    // The schema table itself is stored in the first page.
+    let mut objects = vec![(String::from("sqlite_schema"), 1)];
+
+    objects.extend(connection.prepare(&format!("SELECT name, rootpage FROM `{schema}`.sqlite_schema WHERE rootpage > 0"))?
+        .query_map([], |row| Ok((row.get::<_, String>("name")?, row.get::<_, u64>("rootpage")?)))?
+        .collect::<Result<Vec<_>, _>>()?);
+
+    let mut random = Random::new(connection)?;
+
+    let mut usage = HashMap::with_capacity(objects.len());
+    let mut read_pages = 0;
+
+    for (name, root) in objects {
+        let (object_usage, object_pages) = estimate_usage(&file, root, page_size, usable_size, sampling, &mut random)?;
+
+        usage.insert(name, object_usage);
+
+        read_pages += object_pages;
+    }
+
+    Ok(Some((usage, read_pages)))
+}
+
+/// Get path to the database file, or `None` if the database is stored in memory.
+fn query_path(connection: &rusqlite::Connection, schema: &str) -> anyhow::Result<Option<String>> {
+    let path = connection.query_row("SELECT file FROM pragma_database_list WHERE name = ?1", [schema], |row| {
+        row.get::<_, String>("file")
+    })?;
+
+    // In-memory and temporary databases have no file path.
+    Ok(Some(path).filter(|path| !path.is_empty()))
+}
+
+/// Get size of the database file, or its estimation from the pages count
+/// if the database is not stored on disk.
+fn query_file_size(connection: &rusqlite::Connection, schema: &str, page_size: u64, page_count: u64) -> anyhow::Result<u64> {
+    let metadata = query_path(connection, schema)?
+        .and_then(|path| std::fs::metadata(path).ok());
+
+    match metadata {
+        Some(metadata) if metadata.is_file() => Ok(metadata.len()),
+
+        _ => Ok(page_size * page_count)
+    }
+}
+
+/// Get sizes of the journal files stored next to the database file.
+fn query_journal(connection: &rusqlite::Connection, schema: &str) -> anyhow::Result<Journal> {
+    use std::io::Read;
+
+    let Some(path) = query_path(connection, schema)? else {
+        return Ok(Journal::default());
+    };
+
+    let file_size = |suffix: &str| std::fs::metadata(format!("{path}{suffix}"))
+        .ok()
+        .filter(|metadata| metadata.is_file())
+        .map(|metadata| metadata.len());
+
+    let mut journal = Journal {
+        wal_size: file_size("-wal"),
+        wal_frames: 0,
+        shm_size: file_size("-shm"),
+        journal_size: file_size("-journal")
+    };
+
+    // WAL file starts with a 32 bytes header storing the page size,
+    // followed by frames of a 24 bytes header and a page each.
+    if let Some(wal_size) = journal.wal_size {
+        let mut header = [0; 32];
+
+        let is_read = std::fs::File::open(format!("{path}-wal"))
+            .and_then(|mut file| file.read_exact(&mut header))
+            .is_ok();
+
+        let page_size = u32::from_be_bytes([header[8], header[9], header[10], header[11]]) as u64;
+
+        if is_read && page_size > 0 {
+            journal.wal_frames = wal_size.saturating_sub(32) / (page_size + 24);
+        }
+    }
+
+    Ok(journal)
+}
+
+/// Read header of the database file, or `None` if the database
+/// is stored in memory.
+fn query_header(connection: &rusqlite::Connection, schema: &str) -> anyhow::Result<Option<DatabaseHeader>> {
+    use std::io::Read;
+
+    let Some(path) = query_path(connection, schema)? else {
+        return Ok(None);
+    };
+
+    let mut header = [0; DatabaseHeader::SIZE];
+
+    let mut file = std::fs::File::open(path)?;
+
+    // Newly created databases are empty until the first write.
+    if file.read_exact(&mut header).is_err() {
+        return Ok(None);
+    }
+
+    Ok(Some(DatabaseHeader::parse(&header)?))
+}
+
+/// Get names of all the schemas available in the connection:
+/// `main`, `temp` (if it was used), and attached databases.
+pub fn query_schemas(connection: &rusqlite::Connection) -> anyhow::Result<Vec<String>> {
+    let schemas = connection.prepare("SELECT name FROM pragma_database_list ORDER BY seq")?
+        .query_map([], |row| row.get::<_, String>("name"))?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(schemas)
+}
+
+/// Parameters shared by all the tables of the schema.
+struct Context<'a> {
+    connection: &'a rusqlite::Connection,
+    schema: &'a str,
+    page_size: u64,
+    is_utf16: bool,
+    sampling: Option<Sampling>,
+
+    /// Report table being measured, amount of its measured rows
+    /// and the amount of rows expected to be measured.
+    progress: &'a dyn Fn(&str, u64, u64)
+}
+
+/// Sum and sum of squares of per-row values.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+struct Moments {
+    sum: u64,
+    squares: f64
+}
+
+impl Moments {
+    #[inline]
+    fn insert(&mut self, value: u64) {
+        self.sum += value;
+        self.squares += (value as f64).powi(2);
+    }
+
+    /// Half-width of 95% confidence interval of the sum over `rows`
+    /// rows estimated from the sum over `sample` random rows.
+    fn margin(&self, sample: u64, rows: u64) -> u64 {
+        let n = sample as f64;
+        let total = rows as f64;
+
+        if sample < 2 {
+            return (self.sum as f64 * total / n).round() as u64;
+        }
+
+        let mean = self.sum as f64 / n;
+        let variance = ((self.squares - n * mean * mean) / (n - 1.0)).max(0.0);
+
+        // Finite population correction.
+        let correction = (1.0 - n / total).max(0.0);
+
+        (1.96 * total * (variance / n * correction).sqrt()).round() as u64
+    }
+}
+
+/// Statistics of the column values accumulated row by row.
+#[derive(Debug, Clone)]
+struct ColumnMeter {
+    size: Moments,
+    logical_size: Moments,
+    overflow_size: Moments,
+    nulls: Moments,
+    distinct: DistinctCounter,
+    min_value_size: u64,
+    max_value_size: u64,
+    values_size: u64,
+    storage: [StorageUsage; 5]
+}
+
+impl Default for ColumnMeter {
+    fn default() -> Self {
+        Self {
+            size: Moments::default(),
+            logical_size: Moments::default(),
+            overflow_size: Moments::default(),
+            nulls: Moments::default(),
+            distinct: DistinctCounter::default(),
+            min_value_size: u64::MAX,
+            max_value_size: 0,
+            values_size: 0,
+            storage: [StorageUsage::default(); 5]
+        }
+    }
+}
+
+impl ColumnMeter {
+    /// Count the column value of `length` logical size which uses
+    /// `header` bytes in the record header and `body` bytes in the record body.
+    fn insert(&mut self, value: rusqlite::types::ValueRef, length: u64, header: u64, body: u64) {
+        self.size.insert(header + body);
+        self.logical_size.insert(length);
+
+        let class = &mut self.storage[StorageClass::of(value) as usize];
+
+        class.rows += 1;
+        class.size += header + body;
+
+        if value == rusqlite::types::ValueRef::Null {
+            self.nulls.insert(1);
+        }
+
+        else {
+            self.nulls.insert(0);
+            self.distinct.insert(value);
+
+            self.min_value_size = self.min_value_size.min(body);
+            self.max_value_size = self.max_value_size.max(body);
+            self.values_size += body;
+        }
+    }
+
+    /// Write measured statistics into the column. If `sample` is given
+    /// as `(sampled rows, table rows)` then statistics are estimated
+    /// for the whole table.
+    fn finish(self, column: &mut Column, sample: Option<(u64, u64)>) {
+        let (distinct, is_distinct_estimated) = self.distinct.count();
+
+        column.min_value_size = self.min_value_size.min(self.max_value_size);
+        column.max_value_size = self.max_value_size;
+
+        let Some((sample, rows)) = sample.filter(|(sample, _)| *sample > 0) else {
+            column.size = self.size.sum;
+            column.logical_size = self.logical_size.sum;
+            column.overflow_size = self.overflow_size.sum;
+            column.nulls = self.nulls.sum;
+            column.distinct = distinct;
+            column.is_distinct_estimated = is_distinct_estimated;
+            column.values_size = self.values_size;
+            column.storage = self.storage;
+
+            return;
+        };
+
+        let scale = |value: u64| (value as f64 * rows as f64 / sample as f64).round() as u64;
+
+        column.size = scale(self.size.sum);
+        column.logical_size = scale(self.logical_size.sum);
+        column.overflow_size = scale(self.overflow_size.sum);
+        column.nulls = scale(self.nulls.sum).min(rows);
+        column.values_size = scale(self.values_size);
+
+        column.storage = self.storage.map(|usage| StorageUsage {
+            rows: scale(usage.rows),
+            size: scale(usage.size)
+        });
+
+        // Haas and Stokes Duj1 estimator: the more values are seen
+        // only once in the sample the more unseen values there are.
+        let values = sample - self.nulls.sum;
+        let fraction = sample as f64 / rows as f64;
+
+        // Every other value is seen at least twice, so estimated
+        // distinct values give the lower bound of singletons.
+        let singletons = self.distinct.singletons()
+            .unwrap_or_else(|| (2 * distinct.min(values)).saturating_sub(values));
+
+        let unseen = (1.0 - fraction) * singletons as f64 / values.max(1) as f64;
+
+        let distinct = if unseen < 1.0 {
+            (distinct as f64 / (1.0 - unseen)).round() as u64
+        } else {
+            rows
+        };
+
+        column.distinct = distinct.min(rows - column.nulls);
+        column.is_distinct_estimated = true;
+
+        column.margins = Some(Margins {
+            size: self.size.margin(sample, rows),
+            logical_size: self.logical_size.margin(sample, rows),
+            overflow_size: self.overflow_size.margin(sample, rows),
+            nulls: self.nulls.margin(sample, rows)
+        });
+    }
+}
+
+/// Amount of bytes the column value uses in the record header and body.
+fn column_value_size(
+    value: rusqlite::types::ValueRef,
+    column: &Column,
+    is_rowid: bool,
+    is_utf16: bool
+) -> anyhow::Result<(u64, u64)> {
+    // Rowid alias column is stored as NULL in the record
+    // while its value is stored as a varint in the cell.
+    if is_rowid {
+        return Ok((1, varint_size(value.as_i64()? as u64)));
+    }
+
+    // Virtual generated columns are computed when read.
+    if !column.kind.is_stored() {
+        return Ok((0, 0));
+    }
+
+    Ok(value_size(value, is_utf16, column.affinity == Affinity::Real))
+}
+
+/// Amount of random rowids probed per sampled row before the rowids
+/// are considered too sparse to draw a uniform sample.
+const SAMPLE_ATTEMPTS: u64 = 10;
+
+/// Confidence intervals of the samples which are not uniform are
+/// widened this many times since their real confidence is unknown.
+const APPROXIMATE_MARGINS_SCALE: u64 = 2;
+
+/// Minimal amount of pages read from each level of a b-tree
+/// when its pages usage is estimated.
+const MIN_SAMPLED_PAGES: u64 = 1_000;
+
+/// Amount of measured rows between progress reports.
+const PROGRESS_ROWS: u64 = 10_000;
+
+/// How many times the WAL file should be larger than the database
+/// file to consider it oversized.
+const OVERSIZED_WAL_RATIO: u64 = 4;
+
+/// Minimal difference between the analyzed and the real amount of
+/// rows, absolute and relative, to consider the statistics stale.
+const STALE_ANALYSIS_ROWS: u64 = 100;
+const STALE_ANALYSIS_FRACTION: f64 = 0.25;
+
+/// Source of the index column value in the measured row.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum IndexTerm {
+    /// Table column with the given position.
+    Column(usize),
+
+    /// Expression or the rowid selected after the table columns.
+    Selection(usize)
+}
+
+/// Sizes of the index columns accumulated row by row.
+#[derive(Debug, Clone)]
+struct IndexMeter {
+    /// Source of each index column value and whether it has `REAL` affinity.
+    terms: Vec<(IndexTerm, bool)>,
+
+    /// Selection telling whether the row is stored in the partial index.
+    condition: Option<usize>,
+
+    sizes: Vec<u64>
+}
+
+impl IndexMeter {
+    /// Prepare measuring of the index columns, adding the values which
+    /// are not the table columns to `selections`. Returns `None` if the
+    /// index has an expression which can't be selected.
+    fn new(index: &Index, columns: &[Column], selections: &mut Vec<String>) -> Option<Self> {
+        let mut select = |selection: String| match selections.iter().position(|item| item == &selection) {
+            Some(i) => i,
+            None => {
+                selections.push(selection);
+
+                selections.len() - 1
+            }
+        };
+
+        let mut terms = Vec::with_capacity(index.columns.len());
+
+        for index_column in &index.columns {
+            let column = columns.iter()
+                .position(|column| Some(&column.name) == index_column.name.as_ref());
+
+            let term = match (column, &index_column.expression) {
+                (Some(i), _) => (IndexTerm::Column(i), columns[i].affinity == Affinity::Real),
+                (None, Some(expression)) => (IndexTerm::Selection(select(expression.clone())), false),
+                (None, None) if index_column.cid == -1 => (IndexTerm::Selection(select(String::from("rowid"))), false),
+
+                (None, None) => return None
+            };
+
+            terms.push(term);
+        }
+
+        let condition = match &index.condition {
+            Some(condition) => Some(select(format!("CASE WHEN {condition} THEN 1 ELSE 0 END"))),
+            None if index.is_partial => return None,
+            None => None
+        };
+
+        Some(Self {
+            sizes: vec![0; terms.len()],
+            terms,
+            condition
+        })
+    }
+}
+
+/// Statistics of all the table columns accumulated row by row.
+struct TableMeter {
+    columns: Vec<Column>,
+    rowid_alias: Option<String>,
+    is_utf16: bool,
+    max_local_payload: u64,
+    meters: Vec<ColumnMeter>,
+
+    /// Index values selected in addition to the table columns.
+    selections: Vec<String>,
+
+    /// Meters of the table indexes, or `None` for indexes which can't be measured.
+    indexes: Vec<Option<IndexMeter>>,
+
+    /// Sizes of the column values in the current record.
+    record: Vec<u64>,
+
+    /// Amount of measured rows.
+    rows: u64
+}
+
+impl TableMeter {
+    fn new(columns: &[Column], indexes: &[Index], rowid_alias: Option<&str>, is_utf16: bool, max_local_payload: u64) -> Self {
+        let mut selections = Vec::new();
+
+        let indexes = indexes.iter()
+            .map(|index| IndexMeter::new(index, columns, &mut selections))
+            .collect();
+
+        Self {
+            columns: columns.to_vec(),
+            rowid_alias: rowid_alias.map(String::from),
+            is_utf16,
+            max_local_payload,
+            meters: vec![ColumnMeter::default(); columns.len()],
+            selections,
+            indexes,
+            record: vec![0; columns.len()],
+            rows: 0
+        }
+    }
+
+    /// Columns selection of `value, LENGTH(value)` pairs to measure
+    /// followed by the index values.
+    fn selection(&self) -> String {
+        self.columns.iter()
+            .map(|column| format!("`{0}`, LENGTH(`{0}`)", column.name))
+            .chain(self.selections.iter().cloned())
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    /// Measure the row with columns selected as in `selection`
+    /// starting from the `offset` field.
+    fn insert(&mut self, row: &rusqlite::Row, offset: usize) -> anyhow::Result<()> {
+        let mut header_size = 0;
+        let mut body_size = 0;
+
+        for (i, column) in self.columns.iter().enumerate() {
+            let value = row.get_ref(offset + i * 2)?;
+            let length = row.get::<_, Option<u64>>(offset + i * 2 + 1)?.unwrap_or_default();
+
+            let is_rowid = self.rowid_alias.as_deref() == Some(column.name.as_str());
+
+            let (header, body) = column_value_size(value, column, is_rowid, self.is_utf16)?;
+
+            self.meters[i].insert(value, length, header, body);
+
+            // Rowid alias value is not a part of the record.
+            let body = if is_rowid { 0 } else { body };
+
+            header_size += header;
+            body_size += body;
+
+            self.record[i] = header + body;
+        }
+
+        // Record header starts with its own size varint.
+        header_size += varint_size(header_size + 1);
+
+        let is_overflow = header_size + body_size > self.max_local_payload;
+
+        for (meter, size) in self.meters.iter_mut().zip(&self.record) {
+            meter.overflow_size.insert(if is_overflow { *size } else { 0 });
+        }
+
+        let selections_offset = offset + self.columns.len() * 2;
+
+        for meter in self.indexes.iter_mut().flatten() {
+            if let Some(condition) = meter.condition && row.get::<_, i64>(selections_offset + condition)? == 0 {
+                continue;
+            }
+
+            for ((term, is_real), size) in meter.terms.iter().zip(&mut meter.sizes) {
+                let value = match term {
+                    IndexTerm::Column(i) => row.get_ref(offset + i * 2)?,
+                    IndexTerm::Selection(i) => row.get_ref(selections_offset + i)?
+                };
+
+                // Index records store the rowid alias and generated columns values.
+                let (header, body) = value_size(value, self.is_utf16, *is_real);
+
+                *size += header + body;
+            }
+        }
+
+        self.rows += 1;
+
+        Ok(())
+    }
+
+    /// Write measured statistics into the columns and indexes. If `rows`
+    /// is given then statistics are estimated for this amount of table rows.
+    fn finish(self, columns: &mut [Column], indexes: &mut [Index], rows: Option<u64>) {
+        let sampled_rows = self.rows;
+
+        for (column, meter) in columns.iter_mut().zip(self.meters) {
+            meter.finish(column, rows.map(|rows| (sampled_rows, rows)));
+        }
+
+        let scale = |value: u64| match rows {
+            Some(rows) if sampled_rows > 0 => (value as f64 * rows as f64 / sampled_rows as f64).round() as u64,
+            _ => value
+        };
+
+        for (index, meter) in indexes.iter_mut().zip(self.indexes) {
+            let Some(meter) = meter else {
+                continue;
+            };
+
+            for (column, size) in index.columns.iter_mut().zip(meter.sizes) {
+                column.size = scale(size);
+            }
+        }
+    }
+}
+
+/// Measure columns of the rowid table on a sample of random rows.
+///
+/// Returns the meter and whether the sample is not uniform
+/// because the rowids are too sparse.
+fn query_sample(
+    context: &Context,
+    table: &str,
+    mut meter: TableMeter,
+    sample_size: u64
+) -> anyhow::Result<(TableMeter, bool)> {
+    let Context { connection, schema, .. } = context;
+
+    // MIN() and MAX() are optimized only when used alone.
+    let min_rowid = connection.query_row(&format!("SELECT MIN(rowid) FROM `{schema}`.`{table}`"), [], |row| row.get::<_, Option<i64>>(0))?;
+    let max_rowid = connection.query_row(&format!("SELECT MAX(rowid) FROM `{schema}`.`{table}`"), [], |row| row.get::<_, Option<i64>>(0))?;
+
+    let (Some(min_rowid), Some(max_rowid)) = (min_rowid, max_rowid) else {
+        return Ok((meter, false));
+    };
+
+    let span = (max_rowid as i128 - min_rowid as i128 + 1).min(u64::MAX as i128) as u64;
+
+    let rowid = |offset: u64| (min_rowid as i128 + offset as i128) as i64;
+
+    let mut random = Random::new(connection)?;
+
+    let selection = meter.selection();
+
+    let mut sampled = HashSet::new();
+
+    // Every rowid of the range is equally likely to be probed and
+    // the missing ones are rejected, so each row is equally likely
+    // to be sampled no matter how the rowids are spread.
+    let mut query = connection.prepare(&format!("SELECT rowid, {selection} FROM `{schema}`.`{table}` WHERE rowid = ?1"))?;
+
+    for _ in 0..sample_size.saturating_mul(SAMPLE_ATTEMPTS) {
+        if meter.rows >= sample_size {
+            return Ok((meter, false));
+        }
+
+        let rowid = rowid(random.below(span));
+
+        let mut rows = query.query([rowid])?;
+
+        let Some(row) = rows.next()? else {
+            continue;
+        };
+
+        // Each row is measured only once.
+        if !sampled.insert(rowid) {
+            continue;
+        }
+
+        meter.insert(row, 1)?;
+
+        if meter.rows.is_multiple_of(PROGRESS_ROWS) {
+            (context.progress)(table, meter.rows, sample_size);
+        }
+    }
+
+    if meter.rows >= sample_size {
+        return Ok((meter, false));
+    }
+
+    // Too many probes miss with sparse rowids, so the rest of the rows
+    // is taken after random rowids, preferring rows after the gaps.
+    let mut query = connection.prepare(&format!("SELECT rowid, {selection} FROM `{schema}`.`{table}` WHERE rowid >= ?1 ORDER BY rowid LIMIT 1"))?;
+
+    for _ in meter.rows..sample_size {
+        let mut rows = query.query([rowid(random.below(span))])?;
+
+        let Some(row) = rows.next()? else {
+            continue;
+        };
+
+        if !sampled.insert(row.get::<_, i64>(0)?) {
+            continue;
+        }
+
+        meter.insert(row, 1)?;
+
+        if meter.rows.is_multiple_of(PROGRESS_ROWS) {
+            (context.progress)(table, meter.rows, sample_size);
+        }
+    }
+
+    Ok((meter, true))
+}
+
+/// Query sizes and structure of the table indexes.
+fn query_indexes(
+    connection: &rusqlite::Connection,
+    schema: &str,
+    table: &str,
+    page_size: u64,
+    usage: &HashMap<String, Usage>
+) -> anyhow::Result<Vec<Index>> {
+    let mut query = connection.prepare(&format!("SELECT name, `unique`, origin, partial FROM pragma_index_list('{table}', '{schema}')"))?;
+
+    let mut indexes_raw = query.query_map([], |row| {
+        let name = row.get::<_, String>("name")?;
+        let is_unique = row.get::<_, bool>("unique")?;
+        let origin = row.get::<_, String>("origin")?;
+        let is_partial = row.get::<_, bool>("partial")?;
+
+        Ok((name, is_unique, IndexOrigin::from_code(&origin), is_partial))
+    })?.collect::<Result<Vec<_>, _>>()?;
+
+    let mut indexes = Vec::with_capacity(indexes_raw.len());
+
+    // Key columns of the WITHOUT ROWID table.
+    let mut primary_key = None;
+
+    for (index, is_unique, origin, is_partial) in indexes_raw.drain(..) {
+        let mut columns = query_index_columns(connection, schema, &index)?;
+
+        let sql = connection.query_row(
+            &format!("SELECT sql FROM `{schema}`.sqlite_schema WHERE type = 'index' AND name = ?1"),
+            [&index],
+            |row| row.get::<_, Option<String>>("sql")
+        ).optional()?;
+
+        // Primary key of the WITHOUT ROWID table is the table b-tree
+        // itself and it has no schema entry of its own.
+        let Some(sql) = sql else {
+            primary_key = Some(columns);
+
+            continue;
+        };
+
+        let mut condition = None;
+
+        if let Some((terms, where_clause)) = sql.as_deref().and_then(parse_index_sql) {
+            let expressions = columns.iter_mut()
+                .filter(|column| column.is_key)
+                .zip(terms);
+
+            for (column, term) in expressions {
+                if column.is_expression() {
+                    column.expression = Some(term);
+                }
+            }
+
+            condition = where_clause;
+        }
+
+        // Every index has at least its root page, even when empty.
+        let usage = usage.get(&index)
+            .cloned()
+            .unwrap_or_else(|| Usage::empty(page_size));
+
+        let is_system = index.starts_with("sqlite_autoindex_");
+
+        indexes.push(Index {
+            name: index,
+            size: usage.size,
+            payload: usage.payload,
+            unused: usage.unused,
+            btree: usage.btree,
+            is_system,
+            origin,
+            is_unique,
+            is_partial,
+            sql,
+            condition,
+            columns,
+            redundancy: None,
+            statistics: None
+        });
+    }
+
+    let rowid_alias = query_rowid_alias(connection, schema, table)?;
+
+    find_redundant_indexes(&mut indexes, rowid_alias.as_deref(), primary_key.as_deref());
+
+    indexes.sort_by_key(|index| std::cmp::Reverse(index.size));
+
+    Ok(indexes)
+}
+
+/// Get indexed terms and the `WHERE` clause expression of the `CREATE INDEX` statement.
+///
+/// Sort order and collation are removed from the terms.
+fn parse_index_sql(sql: &str) -> Option<(Vec<String>, Option<String>)> {
+    let mut terms = Vec::new();
+    let mut term = String::new();
+
+    let mut depth = 0;
+    let mut end = None;
+
+    let mut chars = sql.char_indices();
+
+    while let Some((i, char)) = chars.next() {
+        match char {
+            '\'' | '"' | '`' | '[' => {
+                let closing = if char == '[' { ']' } else { char };
+
+                if depth > 0 {
+                    term.push(char);
+                }
+
+                for (_, char) in chars.by_ref() {
+                    if depth > 0 {
+                        term.push(char);
+                    }
+
+                    if char == closing {
+                        break;
+                    }
+                }
+            }
+
+            '(' => {
+                if depth > 0 {
+                    term.push(char);
+                }
+
+                depth += 1;
+            }
+
+            ')' if depth == 1 => {
+                terms.push(std::mem::take(&mut term));
+                end = Some(i + 1);
+
+                break;
+            }
+
+            ')' => {
+                depth -= 1;
+
+                term.push(char);
+            }
+
+            ',' if depth == 1 => terms.push(std::mem::take(&mut term)),
+
+            _ if depth > 0 => term.push(char),
+
+            _ => ()
+        }
+    }
+
+    let terms = terms.into_iter()
+        .map(|term| {
+            let mut term = term.trim();
+
+            for keyword in ["ASC", "DESC"] {
+                term = strip_keyword(term, keyword).unwrap_or(term);
+            }
+
+            // Collation name is the last word of the term.
+            if let Some((expression, collation)) = term.rsplit_once(|char: char| char.is_ascii_whitespace()) &&
+                !collation.contains(')') &&
+                let Some(expression) = strip_keyword(expression.trim_end(), "COLLATE")
+            {
+                term = expression;
+            }
+
+            term.to_string()
+        })
+        .collect();
+
+    let tail = sql[end?..].trim();
+
+    let condition = tail.get(..5)
+        .filter(|keyword| keyword.eq_ignore_ascii_case("where"))
+        .map(|_| tail[5..].trim().trim_end_matches(';').trim_end().to_string());
+
+    Some((terms, condition))
+}
+
+/// Remove the keyword from the end of the SQL expression.
+fn strip_keyword<'a>(expression: &'a str, keyword: &str) -> Option<&'a str> {
+    let start = expression.len().checked_sub(keyword.len())?;
+
+    let (expression, suffix) = (expression.get(..start)?, expression.get(start..)?);
+
+    let is_separated = expression.ends_with(|char: char| char.is_ascii_whitespace() || ")'\"`]".contains(char));
+
+    (is_separated && suffix.eq_ignore_ascii_case(keyword)).then(|| expression.trim_end())
+}
+
+/// Query columns of the index.
+fn query_index_columns(connection: &rusqlite::Connection, schema: &str, index: &str) -> anyhow::Result<Vec<IndexColumn>> {
+    let mut query = connection.prepare(&format!("SELECT cid, name, desc, coll, key FROM pragma_index_xinfo('{index}', '{schema}') ORDER BY seqno"))?;
+
+    let columns = query.query_map([], |row| {
+        Ok(IndexColumn {
+            name: row.get("name")?,
+            cid: row.get("cid")?,
+            expression: None,
+            size: 0,
+            is_descending: row.get("desc")?,
+            collation: row.get::<_, Option<String>>("coll")?.unwrap_or_default(),
+            is_key: row.get("key")?
+        })
+    })?.collect::<Result<Vec<_>, _>>()?;
+
+    Ok(columns)
+}
+
+/// Mark indexes which duplicate another index, are a left prefix of another
+/// index, or of the primary key stored in the table b-tree: either the rowid
+/// alias column or the `primary_key` columns of the WITHOUT ROWID table.
+///
+/// Only indexes created by `CREATE INDEX` are marked because the automatic
+/// ones can't be dropped. Partial and expression indexes are never compared.
+fn find_redundant_indexes(indexes: &mut [Index], rowid_alias: Option<&str>, primary_key: Option<&[IndexColumn]>) {
+    fn keys(index: &Index) -> Option<Vec<&IndexColumn>> {
+        let keys = index.columns.iter()
+            .filter(|column| column.is_key)
+            .collect::<Vec<_>>();
+
+        let is_comparable = !index.is_partial && !keys.is_empty() &&
+            !keys.iter().any(|column| column.is_expression());
+
+        is_comparable.then_some(keys)
+    }
+
+    fn is_prefix(prefix: &[&IndexColumn], keys: &[&IndexColumn]) -> bool {
+        prefix.len() <= keys.len() && prefix.iter()
+            .zip(keys)
+            .all(|(a, b)| a.is_same(b))
+    }
+
+    let keys = indexes.iter()
+        .map(keys)
+        .collect::<Vec<_>>();
+
+    // Which of the duplicating indexes should be kept: automatic indexes
+    // can't be dropped and unique ones enforce a constraint.
+    let preference = |i: usize| (indexes[i].origin == IndexOrigin::Create, !indexes[i].is_unique, &indexes[i].name);
+
+    let mut redundancy = vec![None; indexes.len()];
+
+    for (i, index) in indexes.iter().enumerate() {
+        let Some(index_keys) = &keys[i] else {
+            continue;
+        };
+
+        if index.origin != IndexOrigin::Create {
+            continue;
+        }
+
+        if let (Some(rowid_alias), [key]) = (rowid_alias, index_keys.as_slice()) && key.name.as_deref() == Some(rowid_alias) {
+            redundancy[i] = Some(Redundancy::PrimaryKey);
+
+            continue;
+        }
+
+        if let Some(primary_key) = primary_key {
+            let primary_key = primary_key.iter()
+                .filter(|column| column.is_key)
+                .collect::<Vec<_>>();
+
+            // Unique index is redundant only if it has the same columns.
+            let is_covered = is_prefix(index_keys, &primary_key) &&
+                (!index.is_unique || index_keys.len() == primary_key.len());
+
+            if is_covered {
+                redundancy[i] = Some(Redundancy::PrimaryKey);
+
+                continue;
+            }
+        }
+
+        // Unique index can only be replaced by another unique index.
+        let keeper = (0..indexes.len())
+            .filter(|&j| j != i)
+            .filter(|&j| !index.is_unique || indexes[j].is_unique)
+            .filter(|&j| keys[j].as_ref().is_some_and(|keys| keys.len() == index_keys.len() && is_prefix(index_keys, keys)))
+            .filter(|&j| preference(j) < preference(i))
+            .min_by_key(|&j| preference(j));
+
+        if let Some(j) = keeper {
+            redundancy[i] = Some(Redundancy::Duplicate(indexes[j].name.clone()));
+        }
+    }
+
+    // Prefixes are resolved to the longest index which isn't redundant itself.
+    for (i, index) in indexes.iter().enumerate() {
+        let Some(index_keys) = &keys[i] else {
+            continue;
+        };
+
+        if index.origin != IndexOrigin::Create || index.is_unique || redundancy[i].is_some() {
+            continue;
+        }
+
+        let longest = (0..indexes.len())
+            .filter(|&j| redundancy[j].is_none())
+            .filter(|&j| keys[j].as_ref().is_some_and(|keys| keys.len() > index_keys.len() && is_prefix(index_keys, keys)))
+            .max_by_key(|&j| keys[j].as_ref().map(Vec::len));
+
+        if let Some(j) = longest {
+            redundancy[i] = Some(Redundancy::Prefix(indexes[j].name.clone()));
+        }
+    }
+
+    for (index, redundancy) in indexes.iter_mut().zip(redundancy) {
+        index.redundancy = redundancy;
+    }
+}
+
+/// Query statistics gathered by the last `ANALYZE` of the schema.
+///
+/// Empty statistics are returned if the schema was never analyzed.
+pub fn query_statistics(connection: &rusqlite::Connection, schema: &str) -> anyhow::Result<Statistics> {
+    let mut statistics = Statistics::default();
+
+    let has_table = |table: &str| connection.query_row(
+        &format!("SELECT COUNT(*) FROM `{schema}`.sqlite_schema WHERE type = 'table' AND name = ?1"),
+        [table],
+        |row| row.get::<_, bool>(0)
+    );
+
+    if !has_table("sqlite_stat1")? {
+        return Ok(statistics);
+    }
+
+    let mut query = connection.prepare(&format!("
+        SELECT stat.tbl, stat.idx, stat.stat, COALESCE(list.partial, 0) AS partial
+        FROM `{schema}`.sqlite_stat1 AS stat
+        LEFT JOIN pragma_index_list(stat.tbl, '{schema}') AS list ON list.name = stat.idx
+    "))?;
+
+    let rows = query.query_map([], |row| {
+        let table = row.get::<_, String>("tbl")?;
+        let index = row.get::<_, Option<String>>("idx")?;
+        let stat = row.get::<_, Option<String>>("stat")?;
+        let is_partial = row.get::<_, bool>("partial")?;
+
+        Ok((table, index, stat.unwrap_or_default(), is_partial))
+    })?.collect::<Result<Vec<_>, _>>()?;
+
+    for (table, index, stat, is_partial) in rows {
+        // Integers are followed by optional keywords like `unordered`.
+        let mut numbers = stat.split_ascii_whitespace()
+            .map_while(|number| number.parse::<u64>().ok());
+
+        let Some(rows) = numbers.next() else {
+            continue;
+        };
+
+        // Tables without indexes have a row with `NULL` index name,
+        // and the others have the amount of rows of each full index.
+        if !is_partial {
+            let table_rows = statistics.tables.entry(table).or_default();
+
+            *table_rows = rows.max(*table_rows);
+        }
+
+        if let Some(index) = index {
+            statistics.indexes.insert(index, IndexStatistics {
+                rows,
+                rows_per_key: numbers.collect(),
+                samples: 0
+            });
+        }
+    }
+
+    if has_table("sqlite_stat4")? {
+        let mut query = connection.prepare(&format!("SELECT idx, COUNT(*) FROM `{schema}`.sqlite_stat4 GROUP BY idx"))?;
+
+        let samples = query.query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, u64>(1)?)))?;
+
+        for sample in samples {
+            let (index, samples) = sample?;
+
+            if let Some(statistics) = statistics.indexes.get_mut(&index) {
+                statistics.samples = samples;
+            }
+        }
+    }
+
+    Ok(statistics)
+}
+
+/// Get pages usage and indexes of the table which is pending analysis.
+fn query_pending_table(
+    connection: &rusqlite::Connection,
+    schema: &str,
+    table: &str,
+    table_usage: &Usage,
+    page_size: u64,
+    usage: &HashMap<String, Usage>
+) -> anyhow::Result<Table> {
+    Ok(Table {
+        name: table.to_string(),
+        rows: 0,
+        sampled_rows: None,
+        is_sample_approximate: false,
+        analyzed_rows: None,
+        size: table_usage.size,
+        payload: table_usage.payload,
+        unused: table_usage.unused,
+        btree: table_usage.btree.clone(),
+        is_system: table.starts_with("sqlite_"),
+        is_pending: true,
+        module: None,
+        columns: Vec::new(),
+        indexes: query_indexes(connection, schema, table, page_size, usage)?,
+        shadows: Vec::new()
+    })
+}
+
+/// Query structure and sizes of the table stored in the database.
+fn query_table(
+    context: &Context,
+    table: String,
+    table_usage: Usage,
+    usage: &HashMap<String, Usage>
+) -> anyhow::Result<Table> {
+    let Context { connection, schema, page_size, is_utf16, .. } = *context;
+
+    (context.progress)(&table, 0, 0);
+
+    let (without_rowid, is_strict) = query_table_options(connection, schema, &table)?;
+
+    let rowid_alias = query_rowid_alias(connection, schema, &table)?;
+
+    let mut columns = query_columns(connection, schema, &table, is_strict)?;
+
+    let mut indexes = query_indexes(connection, schema, &table, page_size, usage)?;
+
+    let mut meter = TableMeter::new(&columns, &indexes, rowid_alias.as_deref(), is_utf16, max_local_payload(page_size, without_rowid));
+
+    // Index expressions are parsed from their SQL so if they can't be
+    // selected then only the table columns are measured.
+    if connection.prepare(&format!("SELECT {} FROM `{schema}`.`{table}`", meter.selection())).is_err() {
+        meter = TableMeter::new(&columns, &[], rowid_alias.as_deref(), is_utf16, max_local_payload(page_size, without_rowid));
+    }
+
+    // Sampling requires random access to the rows by their rowid.
+    let sample = match context.sampling {
+        Some(sampling) if !without_rowid => {
+            // Each cell of a rowid table leaf page is a row.
+            let rows = table_usage.btree.leaf_cells;
+
+            Some((sampling.sample_size(rows), rows))
+                .filter(|(sample_size, rows)| sample_size < rows)
+        }
+
+        _ => None
+    };
+
+    let (rows, sampled_rows, is_sample_approximate) = match sample {
+        Some((sample_size, rows)) => {
+            let (meter, is_approximate) = query_sample(context, &table, meter, sample_size)?;
+
+            let sampled_rows = meter.rows;
+
+            meter.finish(&mut columns, &mut indexes, Some(rows));
+
+            if is_approximate {
+                for margins in columns.iter_mut().filter_map(|column| column.margins.as_mut()) {
+                    margins.size *= APPROXIMATE_MARGINS_SCALE;
+                    margins.logical_size *= APPROXIMATE_MARGINS_SCALE;
+                    margins.overflow_size *= APPROXIMATE_MARGINS_SCALE;
+                    margins.nulls *= APPROXIMATE_MARGINS_SCALE;
+                }
+            }
+
+            (rows, Some(sampled_rows), is_approximate)
+        }
+
+        // All the rows and columns are measured in a single table scan.
+        None => {
+            // Records of WITHOUT ROWID tables are stored in all the cells.
+            let expected_rows = if without_rowid {
+                table_usage.btree.leaf_cells + table_usage.btree.interior_cells
+            } else {
+                table_usage.btree.leaf_cells
+            };
+
+            let selection = meter.selection();
+
+            let mut query = connection.prepare(&format!("SELECT {selection} FROM `{schema}`.`{table}`"))?;
+
+            let mut rows = query.query([])?;
+
+            while let Some(row) = rows.next()? {
+                meter.insert(row, 0)?;
+
+                if meter.rows.is_multiple_of(PROGRESS_ROWS) {
+                    (context.progress)(&table, meter.rows, expected_rows);
+                }
+            }
+
+            let rows = meter.rows;
+
+            meter.finish(&mut columns, &mut indexes, None);
+
+            (rows, None, false)
+        }
+    };
+
+    columns.sort_by_key(|column| std::cmp::Reverse(column.size));
+
+    let is_system = table.starts_with("sqlite_");
+
+    Ok(Table {
+        name: table,
+        size: table_usage.size,
+        payload: table_usage.payload,
+        unused: table_usage.unused,
+        btree: table_usage.btree,
+        is_system,
+        is_pending: false,
+        rows,
+        sampled_rows,
+        is_sample_approximate,
+        analyzed_rows: None,
+        module: None,
+        columns,
+        indexes,
+        shadows: Vec::new()
+    })
+}
+
+/// Get name of the virtual table module from its `CREATE VIRTUAL TABLE` statement.
+fn parse_module(sql: &str) -> Option<String> {
+    let mut tokens = sql.split_ascii_whitespace();
+
+    tokens.find(|token| token.eq_ignore_ascii_case("using"))?;
+
+    let module = tokens.next()?
+        .chars()
+        .take_while(|char| char.is_ascii_alphanumeric() || *char == '_')
+        .collect::<String>()
+        .to_ascii_lowercase();
+
+    (!module.is_empty()).then_some(module)
+}
+
+/// Suffixes of the shadow tables created by the virtual table module.
+///
+/// https://sqlite.org/vtab.html#xshadowname
+fn shadow_suffixes(module: &str) -> &'static [&'static str] {
+    match module {
+        "fts5" => &["data", "idx", "content", "docsize", "config"],
+        "fts3" | "fts4" => &["content", "segments", "segdir", "docsize", "stat"],
+        "rtree" | "rtree_i32" | "geopoly" => &["node", "rowid", "parent"],
+
+        _ => &[]
+    }
+}
+
+/// Get names and modules of all the virtual tables in the schema.
+fn query_virtual_tables(connection: &rusqlite::Connection, schema: &str) -> anyhow::Result<Vec<(String, String)>> {
+    let mut query = connection.prepare(&format!("
+        SELECT name, sql FROM `{schema}`.sqlite_schema
+        WHERE type = 'table' AND sql LIKE 'CREATE VIRTUAL TABLE%'
+    "))?;
+
+    let virtual_tables = query.query_map([], |row| {
+        let name = row.get::<_, String>("name")?;
+        let sql = row.get::<_, String>("sql")?;
+
+        Ok((name, sql))
+    })?.filter_map(|row| match row {
+        Ok((name, sql)) => parse_module(&sql).map(|module| Ok((name, module))),
+        Err(err) => Some(Err(err))
+    }).collect::<Result<Vec<_>, _>>()?;
+
+    Ok(virtual_tables)
+}
+
+/// Merge shadow tables of the virtual table into a single table.
+fn query_virtual_table(
+    connection: &rusqlite::Connection,
+    schema: &str,
+    table: String,
+    module: String,
+    mut shadows: Vec<Table>
+) -> Table {
+    let is_pending = shadows.iter().any(|shadow| shadow.is_pending);
+
+    // Virtual table module can be unavailable.
+    let rows = if is_pending {
+        0
+    } else {
+        connection.query_row(&format!("SELECT COUNT(*) FROM `{schema}`.`{table}`"), [], |row| row.get::<_, u64>(0))
+            .unwrap_or_default()
+    };
+
+    let mut btree = BTree::default();
+
+    for shadow in &shadows {
+        btree += &shadow.btree;
+    }
+
+    let mut indexes = shadows.iter()
+        .flat_map(|shadow| shadow.indexes.iter().cloned())
+        .collect::<Vec<_>>();
+
+    indexes.sort_by_key(|index| std::cmp::Reverse(index.size));
+
+    // Values of the virtual table columns are stored in its shadow tables.
+    let columns = query_columns(connection, schema, &table, false)
+        .unwrap_or_default();
+
+    shadows.sort_by_key(|shadow| std::cmp::Reverse(shadow.size));
+
+    Table {
+        name: table,
+        rows,
+        sampled_rows: None,
+        is_sample_approximate: false,
+        analyzed_rows: None,
+        size: shadows.iter().map(|shadow| shadow.size).sum(),
+        payload: shadows.iter().map(|shadow| shadow.payload).sum(),
+        unused: shadows.iter().map(|shadow| shadow.unused).sum(),
+        btree,
+        is_system: false,
+        is_pending,
+        module: Some(module),
+        columns,
+        indexes,
+        shadows
+    }
+}
+
+pub fn query_structure(
+    connection: &rusqlite::Connection,
+    schema: &str,
+    sampling: Option<Sampling>,
+    update: &dyn Fn(Update)
+) -> anyhow::Result<Schema> {
+    update(Update::Progress(Progress {
+        schema: schema.to_string(),
+        ..Progress::default()
+    }));
+
+    // All the attached databases must use the same text encoding.
+    let is_utf16 = connection.query_row("PRAGMA encoding", [], |row| row.get::<_, String>(0))?
+        .starts_with("UTF-16");
+
+    let page_size = connection.query_row(&format!("PRAGMA `{schema}`.page_size"), [], |row| row.get::<_, u64>(0))?;
+    let page_count = connection.query_row(&format!("PRAGMA `{schema}`.page_count"), [], |row| row.get::<_, u64>(0))?;
+    let freelist_count = connection.query_row(&format!("PRAGMA `{schema}`.freelist_count"), [], |row| row.get::<_, u64>(0))?;
+
+    let file_size = query_file_size(connection, schema, page_size, page_count)?;
+
+    let header = query_header(connection, schema)?;
+    let journal = query_journal(connection, schema)?;
+
+    let sampled_usage = match sampling {
+        Some(sampling) => query_sampled_usage(connection, schema, sampling, page_size, page_count, header.as_ref(), &journal)?,
+        None => None
+    };
+
+    // Owners of the pages are known only when every page is read.
+    let (usage, page_map, sampled_pages) = match sampled_usage {
+        Some((usage, sampled_pages)) => (usage, PageMap::default(), Some(sampled_pages)),
+
+        None => {
+            let (usage, page_map) = query_usage(connection, schema, page_count)?;
+
+            (usage, page_map, None)
+        }
+    };
+
+    // Pages count includes pages appended by the not checkpointed
+    // transactions, each of them is at least one WAL frame.
+    let wal_pages = if journal.wal_size.is_some() {
+        page_count.saturating_sub(file_size / page_size).min(journal.wal_frames)
+    } else {
+        0
+    };
+
+    // Every object is a table or an index of some table.
+    let objects_size = usage.values()
+        .map(|usage| usage.size)
+        .sum::<u64>();
+
+    let schema_size = usage.get("sqlite_schema").map(|usage| usage.size).unwrap_or_default();
+
+    // Internal tables and automatic indexes are named `sqlite_*`.
+    let system_size = usage.iter()
+        .filter(|(name, _)| name.starts_with("sqlite_"))
+        .map(|(_, usage)| usage.size)
+        .sum::<u64>();
+
+    let summary = DatabaseSummary {
+        file_size,
+        page_size,
+        page_count,
+        freelist_count,
+        schema_size,
+        system_size,
+        payload: usage.values().map(|usage| usage.payload).sum(),
+        unused: usage.values().map(|usage| usage.unused).sum(),
+        overhead: (file_size + wal_pages * page_size).saturating_sub(objects_size + freelist_count * page_size),
+        wal_pages,
+        locality: usage.values().fold(Locality::default(), |mut locality, usage| {
+            locality += usage.btree.locality;
+
+            locality
+        }),
+        page_map: Arc::new(page_map),
+        sampled_pages,
+        header,
+        journal
+    };
+
+    let mut query = connection.prepare(&format!("SELECT name FROM `{schema}`.sqlite_schema WHERE type = 'table'"))?;
+
+    // Virtual tables don't have any pages.
+    let tables_raw = query.query_map([], |row| row.get::<_, String>("name"))?
+        .filter_map(|table| match table {
+            Ok(table) => usage.get(&table).map(|usage| Ok((table, usage.clone()))),
+            Err(err) => Some(Err(err))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let virtual_tables = query_virtual_tables(connection, schema)?;
+
+    let shadow_tables = connection.prepare(&format!("SELECT name FROM pragma_table_list WHERE schema = '{schema}' AND type = 'shadow'"))?
+        .query_map([], |row| row.get::<_, String>("name"))?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut tables_raw = tables_raw.into_iter()
+        .map(|(table, table_usage)| {
+            // Shadow tables are named `<virtual table>_<suffix>`.
+            let owner = virtual_tables.iter().find(|(name, module)| {
+                table.strip_prefix(name.as_str())
+                    .and_then(|suffix| suffix.strip_prefix('_'))
+                    .is_some_and(|suffix| shadow_suffixes(module).contains(&suffix) || shadow_tables.contains(&table))
+            });
+
+            let owner = owner.map(|(name, _)| name.clone());
+
+            (table, table_usage, owner)
+        })
+        .collect::<Vec<_>>();
+
+    // Report all the tables with their known pages usage before
+    // measuring them one by one.
+    let mut pending_tables = Vec::with_capacity(tables_raw.len());
+    let mut pending_shadows = HashMap::<String, Vec<Table>>::new();
+
+    let statistics = query_statistics(connection, schema)?;
+
+    for (table, table_usage, owner) in &tables_raw {
+        let mut table = query_pending_table(connection, schema, table, table_usage, page_size, &usage)?;
+
+        statistics.apply(&mut table);
+
+        match owner {
+            Some(owner) => pending_shadows.entry(owner.clone()).or_default().push(table),
+            None => pending_tables.push(table)
+        }
+    }
+
+    for (table, module) in &virtual_tables {
+        if let Some(shadows) = pending_shadows.remove(table) {
+            pending_tables.push(query_virtual_table(connection, schema, table.clone(), module.clone(), shadows));
+        }
+    }
+
+    pending_tables.sort_by_key(|table| std::cmp::Reverse(table.size));
+
+    let (views, triggers) = query_schema_objects(connection, schema)?;
+
+    update(Update::Schema(Schema {
+        name: schema.to_string(),
+        summary: summary.clone(),
+        tables: pending_tables,
+        views: views.clone(),
+        triggers: triggers.clone()
+    }));
+
+    let tables_total = tables_raw.len();
+    let tables_done = std::cell::Cell::new(0);
+
+    let report = |table: &str, rows_done: u64, rows: u64| update(Update::Progress(Progress {
+        schema: schema.to_string(),
+        tables_done: tables_done.get(),
+        tables: tables_total,
+        table: Some(table.to_string()),
+        rows_done,
+        rows,
+        ..Progress::default()
+    }));
+
+    let context = Context {
+        connection,
+        schema,
+        page_size,
+        is_utf16,
+        sampling,
+        progress: &report
+    };
+
+    let mut tables = Vec::with_capacity(tables_raw.len());
+    let mut shadows = HashMap::<String, Vec<Table>>::new();
+
+    for (table, table_usage, owner) in tables_raw.drain(..) {
+        let mut table = query_table(&context, table, table_usage, &usage)?;
+
+        statistics.apply(&mut table);
+
+        tables_done.set(tables_done.get() + 1);
+
+        match owner {
+            Some(owner) => shadows.entry(owner).or_default().push(table),
+
+            None => {
+                update(Update::Table {
+                    schema: schema.to_string(),
+                    table: table.clone()
+                });
+
+                tables.push(table);
+            }
+        }
+    }
+
+    for (table, module) in virtual_tables {
+        // Some modules don't store any data in the database.
+        if let Some(shadows) = shadows.remove(&table) {
+            let table = query_virtual_table(connection, schema, table, module, shadows);
+
+            update(Update::Table {
+                schema: schema.to_string(),
+                table: table.clone()
+            });
+
+            tables.push(table);
+        }
+    }
+
+    tables.sort_by_key(|table| std::cmp::Reverse(table.size));
+
+    Ok(Schema {
+        name: schema.to_string(),
+        summary,
+        tables,
+        views,
+        triggers
+    })
+}
+
+/// Table access reported by the SQLite authorizer while preparing a statement.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct Access {
+    /// Trigger or view making the access, or `None` for the statement itself.
+    accessor: Option<String>,
+
+    /// Accessed table, or `None` for the actions not related to tables.
+    table: Option<String>,
+
+    is_write: bool
+}
+
+/// Prepare the statement and get all the actions it would do,
+/// including actions of the views and triggers it uses.
+fn query_accesses(connection: &rusqlite::Connection, schema: &str, sql: &str) -> anyhow::Result<Vec<Access>> {
+    use rusqlite::hooks::{AuthAction, AuthContext, Authorization};
+
+    let accesses = std::sync::Arc::new(spin::Mutex::new(Vec::new()));
+
+    connection.authorizer(Some({
+        let accesses = accesses.clone();
+        let schema = schema.to_string();
+
+        move |context: AuthContext| {
+            let (table, is_write) = match context.action {
+                AuthAction::Read { table_name, .. } => (Some(table_name), false),
+
+                AuthAction::Insert { table_name } |
+                AuthAction::Update { table_name, .. } |
+                AuthAction::Delete { table_name } => (Some(table_name), true),
+
+                _ => (None, false)
+            };
+
+            // Tables of other schemas are qualified by the schema name.
+            let table = table.map(|table| match context.database_name {
+                Some(database) if database != schema && database != "temp" => format!("{database}.{table}"),
+                _ => table.to_string()
+            });
+
+            accesses.lock().push(Access {
+                accessor: context.accessor.map(String::from),
+                table,
+                is_write
+            });
+
+            Authorization::Allow
+        }
+    }));
+
+    let result = connection.prepare(sql).map(|_| ());
+
+    connection.authorizer(None::<fn(AuthContext) -> Authorization>);
+
+    result?;
+
+    Ok(std::mem::take(&mut *accesses.lock()))
+}
+
+/// Query names of the schema tables whose b-trees are opened by the
+/// statement program, e.g. tables joined by `USING` columns which
+/// are not reported by the authorizer.
+fn query_opened_tables(connection: &rusqlite::Connection, schema: &str, sql: &str) -> anyhow::Result<Vec<String>> {
+    let database = connection.query_row("SELECT seq FROM pragma_database_list WHERE name = ?1", [schema], |row| row.get::<_, i64>("seq"))?;
+
+    // Index b-trees are mapped to their tables.
+    let root_pages = connection.prepare(&format!("SELECT rootpage, tbl_name FROM `{schema}`.sqlite_schema WHERE rootpage > 0"))?
+        .query_map([], |row| Ok((row.get::<_, i64>("rootpage")?, row.get::<_, String>("tbl_name")?)))?
+        .collect::<Result<HashMap<_, _>, _>>()?;
+
+    let mut query = connection.prepare(&format!("EXPLAIN {sql}"))?;
+
+    let mut rows = query.query([])?;
+
+    let mut tables = Vec::new();
+
+    while let Some(row) = rows.next()? {
+        let opcode = row.get_ref("opcode")?.as_str()?;
+
+        if matches!(opcode, "OpenRead" | "OpenWrite") &&
+            row.get::<_, i64>("p3")? == database &&
+            let Some(table) = root_pages.get(&row.get::<_, i64>("p2")?)
+        {
+            tables.push(table.clone());
+        }
+    }
+
+    Ok(tables)
+}
+
+/// Get sorted unique names of the accessed tables.
+fn accessed_tables<'a>(accesses: impl IntoIterator<Item = &'a Access>, is_write: bool) -> Vec<String> {
+    let mut tables = accesses.into_iter()
+        .filter(|access| access.is_write == is_write)
+        .filter_map(|access| access.table.clone())
+        .collect::<Vec<_>>();
+
+    tables.sort();
+    tables.dedup();
+
+    tables
+}
+
+/// Query views and triggers of the schema with the tables they use.
+///
+/// Dependencies are reported by the SQLite authorizer while preparing
+/// statements which use the view or fire the trigger, without running them.
+/// Tables read by the views are also taken from their compiled programs.
+fn query_schema_objects(connection: &rusqlite::Connection, schema: &str) -> anyhow::Result<(Vec<View>, Vec<Trigger>)> {
+    let mut query = connection.prepare(&format!("SELECT type, name, tbl_name, sql FROM `{schema}`.sqlite_schema WHERE type IN ('view', 'trigger') ORDER BY name"))?;
+
+    let objects = query.query_map([], |row| {
+        Ok((
+            row.get::<_, String>("type")?,
+            row.get::<_, String>("name")?,
+            row.get::<_, String>("tbl_name")?,
+            row.get::<_, Option<String>>("sql")?.unwrap_or_default()
+        ))
+    })?.collect::<Result<Vec<_>, _>>()?;
+
+    let mut views = Vec::new();
+    let mut triggers = Vec::new();
+
+    // Statements firing triggers of each table.
+    let mut statements = HashMap::<String, Vec<(TriggerEvent, Vec<Access>)>>::new();
+
+    for (kind, name, table, sql) in objects {
+        if kind == "view" {
+            // Views using unknown tables fail to prepare.
+            let accesses = query_accesses(connection, schema, &format!("SELECT * FROM `{schema}`.`{name}`"))
+                .unwrap_or_default();
+
+            let opened_tables = query_opened_tables(connection, schema, &format!("SELECT * FROM `{schema}`.`{name}`"))
+                .unwrap_or_default();
+
+            let mut reads = accessed_tables(&accesses, false);
+
+            reads.extend(opened_tables);
+
+            reads.sort();
+            reads.dedup();
+
+            // Columns of the view itself are read too.
+            reads.retain(|table| table != &name);
+
+            views.push(View {
+                name,
+                sql,
+                reads
+            });
+
+            continue;
+        }
+
+        // Temporary triggers can be attached to the tables of other schemas,
+        // which are searched in the same order as for unqualified names.
+        let table_schema = if schema == "temp" {
+            connection.query_row("
+                SELECT list.schema FROM pragma_table_list AS list
+                JOIN pragma_database_list AS database ON database.name = list.schema
+                WHERE list.name = ?1 COLLATE NOCASE
+                ORDER BY database.seq = 1 DESC, database.seq
+                LIMIT 1
+            ", [&table], |row| row.get::<_, String>("schema")).optional()?
+        } else {
+            None
+        };
+
+        let table_schema = table_schema.unwrap_or_else(|| schema.to_string());
+
+        let table_name = table;
+
+        // Tables of other schemas are qualified the same way as by the authorizer.
+        let table = if table_schema == schema || table_schema == "temp" {
+            table_name.clone()
+        } else {
+            format!("{table_schema}.{table_name}")
+        };
+
+        if !statements.contains_key(&table) {
+            let target = format!("`{table_schema}`.`{table_name}`");
+
+            let columns = connection.prepare(&format!("SELECT name FROM pragma_table_xinfo('{table_name}', '{table_schema}') WHERE hidden = 0"))?
+                .query_map([], |row| row.get::<_, String>("name"))?
+                .collect::<Result<Vec<_>, _>>()?;
+
+            let assignments = columns.iter()
+                .map(|column| format!("`{column}` = `{column}`"))
+                .collect::<Vec<_>>()
+                .join(", ");
+
+            let table_statements = [
+                (TriggerEvent::Insert, format!("INSERT INTO {target} DEFAULT VALUES")),
+                (TriggerEvent::Update, format!("UPDATE {target} SET {assignments}")),
+                (TriggerEvent::Delete, format!("DELETE FROM {target}"))
+            ];
+
+            let table_statements = table_statements.into_iter()
+                .map(|(event, sql)| (event, query_accesses(connection, schema, &sql).unwrap_or_default()))
+                .collect();
+
+            statements.insert(table.clone(), table_statements);
+        }
+
+        let mut event = None;
+        let mut accesses = Vec::new();
+
+        for (statement_event, statement_accesses) in &statements[&table] {
+            let trigger_accesses = statement_accesses.iter()
+                .filter(|access| access.accessor.as_deref() == Some(name.as_str()))
+                .collect::<Vec<_>>();
+
+            if !trigger_accesses.is_empty() {
+                event.get_or_insert(*statement_event);
+                accesses.extend(trigger_accesses);
+            }
+        }
+
+        let reads = accessed_tables(accesses.iter().copied(), false).into_iter()
+            .filter(|read| read != &table)
+            .collect();
+
+        triggers.push(Trigger {
+            reads,
+            writes: accessed_tables(accesses.iter().copied(), true),
+            name,
+            table,
+            event,
+            sql
+        });
+    }
+
+    Ok((views, triggers))
+}
+
+/// Split SQL text into separate statements, dropping comments.
+fn split_statements(sql: &str) -> Vec<String> {
+    let mut statements = Vec::new();
+    let mut statement = String::new();
+
+    let mut chars = sql.chars().peekable();
+
+    while let Some(char) = chars.next() {
+        match char {
+            '\'' | '"' | '`' | '[' => {
+                let closing = if char == '[' { ']' } else { char };
+
+                statement.push(char);
+
+                // Quotes are escaped by doubling them which is
+                // the same as two quoted strings one after another.
+                for char in chars.by_ref() {
+                    statement.push(char);
+
+                    if char == closing {
+                        break;
+                    }
+                }
+            }
+
+            '-' if chars.peek() == Some(&'-') => {
+                for char in chars.by_ref() {
+                    if char == '\n' {
+                        break;
+                    }
+                }
+
+                statement.push(' ');
+            }
+
+            '/' if chars.peek() == Some(&'*') => {
+                chars.next();
+
+                let mut prev = '\0';
+
+                for char in chars.by_ref() {
+                    if prev == '*' && char == '/' {
+                        break;
+                    }
+
+                    prev = char;
+                }
+
+                statement.push(' ');
+            }
+
+            ';' => statements.push(std::mem::take(&mut statement)),
+
+            _ => statement.push(char)
+        }
+    }
+
+    statements.push(statement);
+
+    statements.into_iter()
+        .map(|statement| statement.trim().to_string())
+        .filter(|statement| !statement.is_empty())
+        .collect()
+}
+
+/// Get name of the index used by a step of the query plan,
+/// e.g. `SEARCH t USING COVERING INDEX t_idx (a=?)`.
+fn parse_plan_index(detail: &str) -> Option<&str> {
+    let (_, index) = detail.split_once("USING INDEX ")
+        .or_else(|| detail.split_once("USING COVERING INDEX "))?;
+
+    let index = index.split_once(" (")
+        .map(|(index, _)| index)
+        .unwrap_or(index);
+
+    Some(index)
+}
+
+/// Get schema-qualified names of the indexes used by the query plan.
+///
+/// The plan doesn't tell schemas of the indexes, so they are found
+/// by the root pages of the indexes opened by the query bytecode.
+fn query_plan_indexes(connection: &rusqlite::Connection, sql: &str) -> rusqlite::Result<Vec<String>> {
+    let details = connection.prepare(&format!("EXPLAIN QUERY PLAN {sql}"))?
+        .query_map([], |row| row.get::<_, String>("detail"))?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    // Cursors are opened on the root page (p2) of the database number (p3).
+    let opcodes = connection.prepare(&format!("EXPLAIN {sql}"))?
+        .query_map([], |row| Ok((row.get::<_, String>("opcode")?, row.get::<_, i64>("p2")?, row.get::<_, i64>("p3")?)))?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let databases = connection.prepare("SELECT seq, name FROM pragma_database_list")?
+        .query_map([], |row| Ok((row.get::<_, i64>("seq")?, row.get::<_, String>("name")?)))?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut opened_indexes = Vec::new();
+
+    for (opcode, root_page, database) in opcodes {
+        if !matches!(opcode.as_str(), "OpenRead" | "OpenWrite" | "ReopenIdx") {
+            continue;
+        }
+
+        let Some((_, schema)) = databases.iter().find(|(seq, _)| *seq == database) else {
+            continue;
+        };
+
+        let index = connection.query_row(
+            &format!("SELECT name FROM `{schema}`.sqlite_schema WHERE type = 'index' AND rootpage = ?1"),
+            [root_page],
+            |row| row.get::<_, String>("name")
+        ).optional()?;
+
+        if let Some(index) = index {
+            opened_indexes.push((schema.as_str(), index));
+        }
+    }
+
+    let mut indexes = Vec::<String>::new();
+
+    for index in details.iter().filter_map(|detail| parse_plan_index(detail)) {
+        let Some((schema, _)) = opened_indexes.iter().find(|(_, name)| name == index) else {
+            continue;
+        };
+
+        let index = format!("{schema}.{index}");
+
+        if !indexes.contains(&index) {
+            indexes.push(index);
+        }
+    }
+
+    Ok(indexes)
+}
+
+/// Plan the queries and find indexes used by their plans.
+fn query_plans(connection: &rusqlite::Connection, queries: impl IntoIterator<Item = String>) -> Workload {
+    let queries = queries.into_iter()
+        .map(|sql| match query_plan_indexes(connection, &sql) {
+            Ok(indexes) => WorkloadQuery {
+                sql,
+                indexes,
+                error: None
+            },
+
+            Err(err) => WorkloadQuery {
+                sql,
+                indexes: Vec::new(),
+                error: Some(err.to_string())
+            }
+        })
+        .collect();
+
+    Workload { queries }
+}
+
+/// Run `EXPLAIN QUERY PLAN` for each query of the SQL text
+/// and find indexes used by their plans.
+pub fn query_workload(connection: &rusqlite::Connection, sql: &str) -> anyhow::Result<Workload> {
+    Ok(query_plans(connection, split_statements(sql)))
+}
+
+/// Plan the workload queries again, e.g. once `ANALYZE`
+/// has changed the statistics used by the query planner.
+pub fn query_workload_replan(connection: &rusqlite::Connection, workload: &Workload) -> anyhow::Result<Workload> {
+    Ok(query_plans(connection, workload.queries.iter().map(|query| query.sql.clone())))
+}
+
+/// Query structure of all the schemas available in the connection.
+pub fn query_database(
+    connection: &rusqlite::Connection,
+    sampling: Option<Sampling>,
+    update: &dyn Fn(Update)
+) -> anyhow::Result<Vec<Schema>> {
+    let names = query_schemas(connection)?;
+
+    let mut schemas = Vec::with_capacity(names.len());
+
+    for (i, name) in names.iter().enumerate() {
+        let schema = query_structure(connection, name, sampling, &|schema_update| match schema_update {
+            Update::Progress(progress) => update(Update::Progress(Progress {
+                schemas_done: i,
+                schemas: names.len(),
+                ..progress
+            })),
+
+            schema_update => update(schema_update)
+        })?;
+
+        schemas.push(schema);
+    }
+
+    Ok(schemas)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Create temporary database file with a single wide table.
+    fn wide_table_database(path: &std::path::Path, columns: usize, rows: usize) -> anyhow::Result<rusqlite::Connection> {
+        if path.exists() {
+            std::fs::remove_file(path)?;
+        }
+
+        let connection = rusqlite::Connection::open(path)?;
+
+        let definitions = (0..columns)
+            .map(|i| format!("c{i} {}", ["INTEGER", "TEXT", "REAL", "BLOB"][i % 4]))
+            .collect::<Vec<_>>()
+            .join(", ");
+
+        let values = (0..columns)
+            .map(|i| match i % 4 {
+                0 => format!("n * {i}"),
+                1 => format!("printf('value %d of column {i}', n)"),
+                2 => format!("n / {}.0", i + 1),
+                _ => String::from("randomblob(n % 32)")
+            })
+            .collect::<Vec<_>>()
+            .join(", ");
+
+        connection.execute_batch(&format!("
+            CREATE TABLE wide (id INTEGER PRIMARY KEY, {definitions});
+
+            WITH RECURSIVE numbers(n) AS (SELECT 1 UNION ALL SELECT n + 1 FROM numbers WHERE n < {rows})
+            INSERT INTO wide SELECT n, {values} FROM numbers;
+        "))?;
+
+        Ok(connection)
+    }
+
+    /// Measure the table with `COUNT(*)` and one scan per column.
+    fn measure_per_column(connection: &rusqlite::Connection) -> anyhow::Result<(u64, Vec<Column>)> {
+        let rows = connection.query_row("SELECT COUNT(*) FROM wide", [], |row| row.get::<_, u64>(0))?;
+
+        let mut columns = query_columns(connection, "main", "wide", false)?;
+
+        for column in &mut columns {
+            let mut query = connection.prepare(&format!("SELECT `{0}`, LENGTH(`{0}`) FROM wide", column.name))?;
+
+            let mut rows = query.query([])?;
+
+            let mut meter = ColumnMeter::default();
+
+            while let Some(row) = rows.next()? {
+                let value = row.get_ref(0)?;
+                let length = row.get::<_, Option<u64>>(1)?.unwrap_or_default();
+
+                let (header, body) = column_value_size(value, column, column.name == "id", false)?;
+
+                meter.insert(value, length, header, body);
+            }
+
+            meter.finish(column, None);
+        }
+
+        Ok((rows, columns))
+    }
+
+    /// Compare single-pass table measurement with per-column scans.
+    ///
+    /// Run with `cargo test --release -- --ignored --nocapture`.
+    #[test]
+    #[ignore]
+    fn single_pass_benchmark() -> anyhow::Result<()> {
+        let path = std::env::temp_dir().join("vinolite-single-pass-benchmark.db");
+
+        let connection = wide_table_database(&path, 200, 20_000)?;
+
+        let start = std::time::Instant::now();
+
+        let (rows, columns) = measure_per_column(&connection)?;
+
+        let per_column_time = start.elapsed();
+
+        let start = std::time::Instant::now();
+
+        let Schema { tables, .. } = query_structure(&connection, "main", None, &|_| ())?;
+
+        let single_pass_time = start.elapsed();
+
+        let table = &tables[0];
+
+        assert_eq!(table.rows, rows);
+
+        for column in &columns {
+            let measured = table.columns.iter()
+                .find(|measured| measured.name == column.name)
+                .unwrap();
+
+            assert_eq!(measured.size, column.size);
+            assert_eq!(measured.logical_size, column.logical_size);
+        }
+
+        println!("Per-column scans: {per_column_time:?}");
+        println!("Single pass: {single_pass_time:?}");
+        println!("Speedup: {:.1}x", per_column_time.as_secs_f64() / single_pass_time.as_secs_f64());
+
+        assert!(single_pass_time < per_column_time);
+
+        drop(connection);
+
+        std::fs::remove_file(path)?;
+
+        Ok(())
+    }
+
+    /// Create in-memory database with a `records` table and
+    /// an index created by the `index` statement.
+    fn index_database(index: &str, rows: usize) -> anyhow::Result<rusqlite::Connection> {
+        let connection = rusqlite::Connection::open_in_memory()?;
+
+        connection.execute_batch(&format!("
+            CREATE TABLE records (id INTEGER PRIMARY KEY, name TEXT, value INTEGER);
+
+            {index};
+
+            WITH RECURSIVE numbers(n) AS (SELECT 1 UNION ALL SELECT n + 1 FROM numbers WHERE n < {rows})
+            INSERT INTO records (name, value) SELECT printf('Record %d', n), n FROM numbers WHERE n <= {rows};
+        "))?;
+
+        Ok(connection)
+    }
+
+    /// Get the analyzed index of the `records` table.
+    fn query_records_index(connection: &rusqlite::Connection, index: &str) -> anyhow::Result<Index> {
+        let Schema { summary, tables, .. } = query_structure(connection, "main", None, &|_| ())?;
+
+        let table = tables.into_iter()
+            .find(|table| table.name == "records")
+            .ok_or_else(|| anyhow::anyhow!("Table `records` is missing"))?;
+
+        let index = table.indexes.into_iter()
+            .find(|item| item.name == index)
+            .ok_or_else(|| anyhow::anyhow!("Index `{index}` is missing"))?;
+
+        assert!(index.size >= summary.page_size);
+
+        Ok(index)
+    }
+
+    #[test]
+    fn empty_index() -> anyhow::Result<()> {
+        let connection = index_database("CREATE INDEX records_name ON records (name)", 0)?;
+
+        let index = query_records_index(&connection, "records_name")?;
+
+        assert_eq!(index.btree.leaf_pages, 1);
+        assert_eq!(index.btree.leaf_cells, 0);
+
+        assert!(index.columns.iter().all(|column| column.size == 0));
+
+        Ok(())
+    }
+
+    #[test]
+    fn expression_index() -> anyhow::Result<()> {
+        let connection = index_database("CREATE INDEX records_lower_name ON records (lower(name) COLLATE NOCASE DESC)", 1000)?;
+
+        let index = query_records_index(&connection, "records_lower_name")?;
+
+        assert_eq!(index.btree.leaf_cells + index.btree.interior_cells, 1000);
+
+        assert!(index.columns[0].is_expression());
+        assert_eq!(index.columns[0].expression.as_deref(), Some("lower(name)"));
+        assert!(index.columns[0].is_descending);
+
+        // Each value is a 1 byte serial type and 8 to 11 bytes of text.
+        assert_eq!(index.columns[0].size, 1000 + 9 * 8 + 90 * 9 + 900 * 10 + 11);
+
+        Ok(())
+    }
+
+    #[test]
+    fn partial_index() -> anyhow::Result<()> {
+        let connection = index_database("CREATE INDEX records_large ON records (value) WHERE value > 900", 1000)?;
+
+        let index = query_records_index(&connection, "records_large")?;
+
+        assert!(index.is_partial);
+        assert_eq!(index.condition.as_deref(), Some("value > 900"));
+        assert_eq!(index.btree.leaf_cells, 100);
+
+        // Values and rowids from 901 to 1000 are 2 bytes integers.
+        assert_eq!(index.columns[0].size, 300);
+        assert_eq!(index.columns[1].size, 300);
+
+        Ok(())
+    }
+
+    #[test]
+    fn empty_partial_index() -> anyhow::Result<()> {
+        let connection = index_database("CREATE INDEX records_none ON records (name) WHERE value < 0", 1000)?;
+
+        let index = query_records_index(&connection, "records_none")?;
+
+        assert_eq!(index.btree.leaf_cells, 0);
+        assert!(index.columns.iter().all(|column| column.size == 0));
+
+        Ok(())
+    }
+
+    #[test]
+    fn index_without_pages_usage() -> anyhow::Result<()> {
+        let connection = index_database("CREATE INDEX records_name ON records (name)", 10)?;
+
+        let indexes = query_indexes(&connection, "main", "records", 4096, &HashMap::new())?;
+
+        assert_eq!(indexes.len(), 1);
+        assert_eq!(indexes[0].size, 4096);
+        assert_eq!(indexes[0].btree.leaf_pages, 1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn without_rowid_primary_key() -> anyhow::Result<()> {
+        let connection = rusqlite::Connection::open_in_memory()?;
+
+        connection.execute_batch("
+            CREATE TABLE pairs (a, b, c, PRIMARY KEY (a, b)) WITHOUT ROWID;
+
+            CREATE INDEX pairs_a ON pairs (a);
+            CREATE INDEX pairs_c ON pairs (c);
+
+            INSERT INTO pairs VALUES (1, 2, 3);
+        ")?;
+
+        let Schema { tables, .. } = query_structure(&connection, "main", None, &|_| ())?;
+
+        let mut indexes = tables[0].indexes.iter()
+            .map(|index| (index.name.as_str(), index.redundancy.clone()))
+            .collect::<Vec<_>>();
+
+        indexes.sort_by_key(|(name, _)| *name);
+
+        // Primary key is the table b-tree and not a separate index.
+        assert_eq!(indexes, [
+            ("pairs_a", Some(Redundancy::PrimaryKey)),
+            ("pairs_c", None)
+        ]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn views_and_triggers_dependencies() -> anyhow::Result<()> {
+        let connection = rusqlite::Connection::open_in_memory()?;
+
+        connection.execute_batch("
+            CREATE TABLE records (name TEXT, value INTEGER);
+            CREATE TABLE history (name TEXT);
+
+            CREATE VIEW named AS SELECT name FROM records;
+            CREATE VIEW named_history AS SELECT named.name FROM named JOIN history USING (name);
+
+            CREATE TRIGGER records_insert AFTER INSERT ON records BEGIN
+                INSERT INTO history SELECT name FROM named WHERE name = NEW.name;
+            END;
+        ")?;
+
+        let (views, triggers) = query_schema_objects(&connection, "main")?;
+
+        assert_eq!(views[0].name, "named");
+        assert_eq!(views[0].reads, ["records"]);
+
+        assert_eq!(views[1].name, "named_history");
+        assert_eq!(views[1].reads, ["history", "named", "records"]);
+
+        assert_eq!(triggers[0].table, "records");
+        assert_eq!(triggers[0].event, Some(TriggerEvent::Insert));
+        assert_eq!(triggers[0].reads, ["named"]);
+        assert_eq!(triggers[0].writes, ["history"]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn stale_statistics() -> anyhow::Result<()> {
+        let connection = index_database("CREATE INDEX records_name ON records (name)", 1000)?;
+
+        connection.execute_batch("
+            CREATE INDEX records_small ON records (value) WHERE value < 10;
+
+            ANALYZE;
+
+            INSERT INTO records (name, value) SELECT name, value FROM records;
+        ")?;
+
+        let Schema { tables, .. } = query_structure(&connection, "main", None, &|_| ())?;
+
+        let table = tables.into_iter()
+            .find(|table| table.name == "records")
+            .ok_or_else(|| anyhow::anyhow!("Table `records` is missing"))?;
+
+        // Partial index rows don't count as the table rows.
+        assert_eq!(table.rows, 2000);
+        assert_eq!(table.analyzed_rows, Some(1000));
+        assert!(table.is_analysis_stale());
+
+        let statistics = table.indexes.iter()
+            .map(|index| (index.name.as_str(), index.statistics.clone().map(|statistics| (statistics.rows, statistics.rows_per_key))))
+            .collect::<HashMap<_, _>>();
+
+        assert_eq!(statistics["records_name"], Some((1000, vec![1])));
+        assert_eq!(statistics["records_small"], Some((9, vec![1])));
+
+        Ok(())
+    }
+
+    #[test]
+    fn distinct_counter_switch() {
+        use rusqlite::types::ValueRef;
+
+        let mut counter = DistinctCounter::default();
+
+        for value in 0..EXACT_DISTINCT_LIMIT as i64 {
+            counter.insert(ValueRef::Integer(value));
+            counter.insert(ValueRef::Real(value as f64));
+        }
+
+        assert_eq!(counter.count(), (EXACT_DISTINCT_LIMIT as u64, false));
+        assert_eq!(counter.singletons(), Some(0));
+        assert!(counter.registers.is_empty());
+
+        // Values seen before the switch are kept in the registers.
+        for value in 0..50_000 {
+            counter.insert(ValueRef::Integer(value));
+        }
+
+        let (distinct, is_estimated) = counter.count();
+
+        assert!(is_estimated);
+        assert!(distinct.abs_diff(50_000) < 2_500, "{distinct}");
+        assert_eq!(counter.singletons(), None);
+        assert_eq!(counter.registers.len(), 1 << HYPERLOGLOG_PRECISION);
+    }
+
+    #[test]
+    fn fragmented_page_map() -> anyhow::Result<()> {
+        let connection = rusqlite::Connection::open_in_memory()?;
+
+        // Interleave pages of two tables and free some of them.
+        connection.execute_batch("
+            CREATE TABLE first (value TEXT);
+            CREATE TABLE second (value TEXT);
+            CREATE TABLE dropped (value TEXT);
+        ")?;
+
+        for i in 0..200 {
+            for table in ["first", "second", "dropped"] {
+                connection.execute(&format!("INSERT INTO {table} VALUES (?1)"), [format!("{i:0>1000}")])?;
+            }
+        }
+
+        connection.execute_batch("DROP TABLE dropped")?;
+
+        let page_count = connection.query_row("PRAGMA page_count", [], |row| row.get::<_, u64>(0))?;
+
+        let (_, page_map) = query_usage(&connection, "main", page_count)?;
+
+        let expected = connection.prepare("SELECT pageno, name FROM dbstat('main') ORDER BY pageno")?
+            .query_map([], |row| Ok((row.get::<_, u64>(0)?, row.get::<_, String>(1)?)))?
+            .collect::<Result<HashMap<_, _>, _>>()?;
+
+        let mut next_page = 1;
+
+        for run in &page_map.runs {
+            assert_eq!(run.first_page, next_page);
+            assert!(run.pages > 0);
+
+            for page in run.first_page..run.first_page + run.pages {
+                assert_eq!(expected.get(&page), run.owner.map(|owner| &page_map.objects[owner]));
+            }
+
+            next_page += run.pages;
+        }
+
+        assert_eq!(next_page, page_count + 1);
+
+        // Neighbouring sequences always have different owners.
+        assert!(page_map.runs.windows(2).all(|runs| runs[0].owner != runs[1].owner));
+        assert!(page_map.runs.iter().any(|run| run.owner.is_none()));
+
+        Ok(())
+    }
+
+    #[test]
+    fn sampled_pages_usage() -> anyhow::Result<()> {
+        let path = std::env::temp_dir().join(format!("vinolite-sampled-{}.db", std::process::id()));
+
+        let connection = rusqlite::Connection::open(&path)?;
+
+        // Small pages make deep b-trees with overflow pages.
+        connection.execute_batch("
+            PRAGMA page_size = 512;
+
+            CREATE TABLE records (name TEXT, value BLOB);
+            CREATE INDEX records_name ON records (name);
+
+            WITH RECURSIVE numbers(n) AS (SELECT 1 UNION ALL SELECT n + 1 FROM numbers WHERE n < 20000)
+            INSERT INTO records SELECT hex(randomblob(8)), randomblob(IIF(n % 100 = 0, 2000, 40)) FROM numbers;
+        ")?;
+
+        let result = (|| {
+            let page_count = connection.query_row("PRAGMA page_count", [], |row| row.get::<_, u64>(0))?;
+
+            let header = query_header(&connection, "main")?;
+            let journal = query_journal(&connection, "main")?;
+
+            let (exact, _) = query_usage(&connection, "main", page_count)?;
+
+            let query = |sampling| query_sampled_usage(&connection, "main", sampling, 512, page_count, header.as_ref(), &journal);
+
+            let (full, read_pages) = query(Sampling::Fraction(1.0))?
+                .ok_or_else(|| anyhow::anyhow!("Database file must be sampled"))?;
+
+            // Overflow pages are counted from the payload sizes without reading them.
+            let overflow_pages = exact.values().map(|usage| usage.btree.overflow_pages).sum::<u64>();
+
+            assert!(overflow_pages > 0);
+            assert_eq!(read_pages + overflow_pages, page_count);
+
+            // Locality is estimated only from the jumps between siblings.
+            let without_locality = |usage: &HashMap<String, Usage>| {
+                usage.iter()
+                    .map(|(name, usage)| {
+                        let mut usage = usage.clone();
+
+                        usage.btree.locality = Locality::default();
+
+                        (name.clone(), usage)
+                    })
+                    .collect::<HashMap<_, _>>()
+            };
+
+            assert_eq!(without_locality(&full), without_locality(&exact));
+
+            let (sampled, read_pages) = query(Sampling::Rows(1))?
+                .ok_or_else(|| anyhow::anyhow!("Database file must be sampled"))?;
+
+            assert!(read_pages < page_count);
+
+            for name in ["records", "records_name"] {
+                let (sampled, exact) = (&sampled[name], &exact[name]);
+
+                assert_eq!(sampled.btree.depth, exact.btree.depth);
+                assert!(sampled.btree.leaf_cells.abs_diff(exact.btree.leaf_cells) < exact.btree.leaf_cells / 20, "{name}: {} != {}", sampled.btree.leaf_cells, exact.btree.leaf_cells);
+                assert!(sampled.size.abs_diff(exact.size) < exact.size / 10, "{name}: {} != {}", sampled.size, exact.size);
+            }
+
+            assert_eq!(query_sampled_usage(&rusqlite::Connection::open_in_memory()?, "main", Sampling::Rows(1), 4096, 1, None, &Journal::default())?, None);
+
+            anyhow::Ok(())
+        })();
+
+        drop(connection);
+
+        let _ = std::fs::remove_file(&path);
+
+        result
+    }
+
+    #[test]
+    fn uniform_rows_sample() -> anyhow::Result<()> {
+        let connection = rusqlite::Connection::open_in_memory()?;
+
+        // Rows with NULLs have a gap before each of them, so they would
+        // be sampled twice as often if taken after random rowids.
+        connection.execute_batch("
+            CREATE TABLE dense (value INTEGER);
+            CREATE TABLE sparse (value INTEGER);
+
+            WITH RECURSIVE numbers(n) AS (SELECT 1 UNION ALL SELECT n + 1 FROM numbers WHERE n < 1000)
+            INSERT INTO dense (rowid, value) SELECT n, n FROM numbers UNION ALL SELECT 1000 + n * 2, NULL FROM numbers;
+
+            WITH RECURSIVE numbers(n) AS (SELECT 1 UNION ALL SELECT n + 1 FROM numbers WHERE n < 100)
+            INSERT INTO sparse (rowid, value) SELECT n * 1000000, n FROM numbers;
+        ")?;
+
+        let Schema { tables, .. } = query_structure(&connection, "main", Some(Sampling::Rows(1000)), &|_| ())?;
+
+        let dense = tables.iter()
+            .find(|table| table.name == "dense")
+            .ok_or_else(|| anyhow::anyhow!("Table `dense` is missing"))?;
+
+        assert_eq!(dense.rows, 2000);
+        assert_eq!(dense.sampled_rows, Some(1000));
+        assert!(!dense.is_sample_approximate);
+        assert!(dense.columns[0].nulls.abs_diff(1000) < 150, "{}", dense.columns[0].nulls);
+
+        let Schema { tables, .. } = query_structure(&connection, "main", Some(Sampling::Rows(10)), &|_| ())?;
+
+        let sparse = tables.iter()
+            .find(|table| table.name == "sparse")
+            .ok_or_else(|| anyhow::anyhow!("Table `sparse` is missing"))?;
+
+        assert!(sparse.sampled_rows.is_some());
+        assert!(sparse.is_sample_approximate);
+
+        Ok(())
+    }
+
+    #[test]
+    fn database_header() -> anyhow::Result<()> {
+        let path = std::env::temp_dir().join(format!("vinolite-header-{}.db", std::process::id()));
+
+        let connection = rusqlite::Connection::open(&path)?;
+
+        connection.execute_batch("
+            PRAGMA page_size = 8192;
+            PRAGMA auto_vacuum = INCREMENTAL;
+            PRAGMA user_version = 42;
+            PRAGMA application_id = 1234;
+
+            CREATE TABLE records (name TEXT);
+        ")?;
+
+        let header = query_header(&connection, "main");
+
+        drop(connection);
+
+        std::fs::remove_file(&path)?;
+
+        let header = header?.ok_or_else(|| anyhow::anyhow!("Database header is missing"))?;
+
+        assert_eq!(header.page_size, 8192);
+        assert_eq!(header.user_version, 42);
+        assert_eq!(header.application_id, 1234);
+        assert_eq!(header.encoding(), "UTF-8");
+        assert!(header.is_auto_vacuum());
+        assert!(header.incremental_vacuum);
+        assert!(header.is_page_count_valid());
+
+        assert_eq!(query_header(&rusqlite::Connection::open_in_memory()?, "main")?, None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn wal_journal() -> anyhow::Result<()> {
+        let path = std::env::temp_dir().join(format!("vinolite-wal-{}.db", std::process::id()));
+
+        let connection = rusqlite::Connection::open(&path)?;
+
+        connection.execute_batch("
+            PRAGMA page_size = 4096;
+            PRAGMA journal_mode = WAL;
+            PRAGMA wal_autocheckpoint = 0;
+
+            CREATE TABLE records (name TEXT);
+
+            INSERT INTO records VALUES ('a');
+            INSERT INTO records VALUES ('b');
+        ")?;
+
+        let journal = query_journal(&connection, "main");
+        let structure = query_structure(&connection, "main", None, &|_| ());
+
+        drop(connection);
+
+        for suffix in ["", "-wal", "-shm"] {
+            let _ = std::fs::remove_file(format!("{}{suffix}", path.to_string_lossy()));
+        }
+
+        let journal = journal?;
+
+        // Every transaction appends the modified pages to the WAL.
+        assert_eq!(journal.wal_size, Some(32 + journal.wal_frames * (4096 + 24)));
+        assert!(journal.wal_frames >= 3);
+        assert!(journal.shm_size.is_some());
+        assert_eq!(journal.journal_size, None);
+
+        // Pages of the not checkpointed transactions are not in the database file yet.
+        let Schema { summary, .. } = structure?;
+
+        assert!(summary.wal_pages > 0);
+        assert!(summary.file_size + summary.wal_pages * 4096 >= summary.page_count * 4096);
+        assert_eq!(summary.overhead, 0);
+
+        assert_eq!(query_journal(&rusqlite::Connection::open_in_memory()?, "main")?, Journal::default());
+
+        Ok(())
+    }
+
+    #[test]
+    fn descending_integer_primary_key() -> anyhow::Result<()> {
+        let connection = rusqlite::Connection::open_in_memory()?;
+
+        connection.execute_batch("
+            CREATE TABLE ascending (id INTEGER PRIMARY KEY ASC, name TEXT);
+            CREATE TABLE descending (id INTEGER PRIMARY KEY DESC, name TEXT);
+        ")?;
+
+        assert_eq!(query_rowid_alias(&connection, "main", "ascending")?.as_deref(), Some("id"));
+
+        // `INTEGER PRIMARY KEY DESC` isn't the rowid alias and has its own index.
+        assert_eq!(query_rowid_alias(&connection, "main", "descending")?, None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn column_affinity() {
+        let cases = [
+            ("CHARINT", false, Affinity::Integer),
+            ("FLOATING POINT", false, Affinity::Integer),
+            ("VARCHAR(255)", false, Affinity::Text),
+            ("", false, Affinity::Blob),
+            ("DOUBLE", false, Affinity::Real),
+            ("DECIMAL(10, 5)", false, Affinity::Numeric),
+            ("ANY", false, Affinity::Numeric),
+            ("ANY", true, Affinity::Any),
+            ("any", true, Affinity::Any)
+        ];
+
+        for (declared_type, is_strict, affinity) in cases {
+            assert_eq!(Affinity::from_declared_type(declared_type, is_strict), affinity, "{declared_type:?}");
+        }
+    }
+
+    #[test]
+    fn not_stored_columns() -> anyhow::Result<()> {
+        let connection = rusqlite::Connection::open_in_memory()?;
+
+        connection.execute_batch("
+            CREATE TABLE records (name TEXT, upper_name TEXT AS (upper(name)) VIRTUAL);
+            CREATE VIRTUAL TABLE documents USING fts5 (title, body);
+
+            INSERT INTO records (name) VALUES ('a'), ('b');
+            INSERT INTO documents VALUES ('a', 'b');
+        ")?;
+
+        let Schema { tables, .. } = query_structure(&connection, "main", None, &|_| ())?;
+
+        let columns = tables.iter()
+            .flat_map(|table| table.columns.iter().map(move |column| {
+                (format!("{}.{}", table.name, column.name), (table.stores_column(column), column.size))
+            }))
+            .collect::<HashMap<_, _>>();
+
+        assert_eq!(columns["records.name"], (true, 4));
+        assert_eq!(columns["records.upper_name"], (false, 0));
+
+        // Hidden columns are named after the virtual table and its rank.
+        assert_eq!(columns["documents.title"], (false, 0));
+        assert_eq!(columns["documents.documents"], (false, 0));
+        assert_eq!(columns["documents.rank"], (false, 0));
+
+        Ok(())
+    }
+
+    #[test]
+    fn workload_schemas() -> anyhow::Result<()> {
+        let connection = rusqlite::Connection::open_in_memory()?;
+
+        connection.execute_batch("
+            ATTACH DATABASE ':memory:' AS other;
+
+            CREATE TABLE main.items (a, b);
+            CREATE TABLE other.items (a, b);
+
+            CREATE INDEX main.items_a ON items (a);
+            CREATE INDEX other.items_a ON items (a);
+            CREATE INDEX other.items_b ON items (b);
+        ")?;
+
+        let workload = query_workload(&connection, "
+            SELECT b FROM other.items WHERE a = 1;
+            SELECT a FROM other.items WHERE b = 1 OR a = 2;
+            SELECT * FROM missing;
+        ")?;
+
+        let indexes = workload.queries.iter()
+            .map(|query| query.indexes.clone())
+            .collect::<Vec<_>>();
+
+        assert_eq!(indexes[0], ["other.items_a"]);
+        assert_eq!(indexes[1], ["other.items_b", "other.items_a"]);
+        assert!(indexes[2].is_empty());
+
+        assert_eq!(workload.uses("main", "items_a"), 0);
+        assert_eq!(workload.uses("other", "items_a"), 2);
+        assert_eq!(workload.uses("other", "items_b"), 1);
+        assert_eq!(workload.failed(), 1);
+
+        // Plans are made again with the same queries.
+        assert_eq!(query_workload_replan(&connection, &workload)?, workload);
+
+        Ok(())
+    }
+
+    /// Get redundancy of every index of the `items (a, b, c UNIQUE, d)`
+    /// table created by the `indexes` statements, sorted by names.
+    fn query_redundancy(indexes: &str) -> anyhow::Result<Vec<(String, Option<Redundancy>)>> {
+        let connection = rusqlite::Connection::open_in_memory()?;
+
+        connection.execute_batch(&format!("
+            CREATE TABLE items (a, b, c UNIQUE, d);
+
+            {indexes}
+        "))?;
+
+        let mut indexes = query_indexes(&connection, "main", "items", 4096, &HashMap::new())?
+            .into_iter()
+            .map(|index| (index.name, index.redundancy))
+            .collect::<Vec<_>>();
+
+        indexes.sort_by(|a, b| a.0.cmp(&b.0));
+
+        Ok(indexes)
+    }
+
+    #[test]
+    fn duplicate_indexes() -> anyhow::Result<()> {
+        let indexes = query_redundancy("
+            CREATE INDEX items_a_1 ON items (a, b);
+            CREATE INDEX items_a_2 ON items (a, b);
+        ")?;
+
+        // Only one of the identical indexes should be dropped.
+        assert_eq!(indexes[0], (String::from("items_a_1"), None));
+        assert_eq!(indexes[1], (String::from("items_a_2"), Some(Redundancy::Duplicate(String::from("items_a_1")))));
+
+        Ok(())
+    }
+
+    #[test]
+    fn prefix_indexes() -> anyhow::Result<()> {
+        let indexes = query_redundancy("
+            CREATE UNIQUE INDEX items_a ON items (a);
+            CREATE INDEX items_a_b ON items (a, b);
+            CREATE INDEX items_d ON items (d);
+            CREATE INDEX items_d_b ON items (d, b);
+        ")?;
+
+        // Unique index enforces a constraint the longer index doesn't.
+        assert_eq!(indexes[0], (String::from("items_a"), None));
+        assert_eq!(indexes[1], (String::from("items_a_b"), None));
+
+        assert_eq!(indexes[2], (String::from("items_d"), Some(Redundancy::Prefix(String::from("items_d_b")))));
+        assert_eq!(indexes[3], (String::from("items_d_b"), None));
+
+        Ok(())
+    }
+
+    #[test]
+    fn unique_constraint_duplicate() -> anyhow::Result<()> {
+        let indexes = query_redundancy("CREATE INDEX items_c ON items (c)")?;
+
+        assert_eq!(indexes, [
+            (String::from("items_c"), Some(Redundancy::Duplicate(String::from("sqlite_autoindex_items_1")))),
+            (String::from("sqlite_autoindex_items_1"), None)
+        ]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn different_order_and_collation() -> anyhow::Result<()> {
+        let indexes = query_redundancy("
+            CREATE INDEX items_d ON items (d);
+            CREATE INDEX items_d_desc ON items (d DESC);
+            CREATE INDEX items_d_nocase ON items (d COLLATE NOCASE);
+            CREATE INDEX items_d_nocase_b ON items (d COLLATE NOCASE, b);
+        ")?;
+
+        assert_eq!(indexes[0], (String::from("items_d"), None));
+        assert_eq!(indexes[1], (String::from("items_d_desc"), None));
+        assert_eq!(indexes[2], (String::from("items_d_nocase"), Some(Redundancy::Prefix(String::from("items_d_nocase_b")))));
+        assert_eq!(indexes[3], (String::from("items_d_nocase_b"), None));
+
+        Ok(())
+    }
+
+    #[test]
+    fn temp_trigger_on_main_table() -> anyhow::Result<()> {
+        let connection = rusqlite::Connection::open_in_memory()?;
+
+        connection.execute_batch("
+            CREATE TABLE records (name TEXT);
+            CREATE TABLE history (name TEXT);
+
+            CREATE TEMP TRIGGER records_update AFTER UPDATE ON records BEGIN
+                INSERT INTO history SELECT name FROM records WHERE name = NEW.name;
+            END;
+        ")?;
+
+        let (_, triggers) = query_schema_objects(&connection, "temp")?;
+
+        assert_eq!(triggers[0].table, "main.records");
+        assert_eq!(triggers[0].event, Some(TriggerEvent::Update));
+        assert!(triggers[0].reads.is_empty());
+        assert_eq!(triggers[0].writes, ["main.history"]);
+
+        Ok(())
+    }
 }
@@ -1,5 +1,7 @@
 use std::path::PathBuf;
 
+use db_stats::Sampling;
+
 pub mod db_stats;
 pub mod tui;
 
@@ -11,30 +13,114 @@ under certain conditions.
 
 Analyze SQLite databases space use per table, column and index.
 
-Usage: vinolite <database path>";
+Usage: vinolite [options] <database path> [attached database path...]
+
+Options:
+  --sample <percent>%  Estimate statistics from a percent of rows and pages
+  --sample <rows>      Estimate statistics from an amount of rows
+  --workload <path>    Find indexes unused by queries from the SQL file
+
+Sampling estimates pages usage and amounts of rows from at least 1000
+pages of each b-tree level, and columns statistics from random rows.
+Databases stored in memory or with pages in the WAL are still read
+page by page, and the page map is shown only then.";
 
 fn main() -> anyhow::Result<()> {
-    let args = std::env::args().collect::<Vec<String>>();
+    let mut args = std::env::args().skip(1);
+
+    let mut sampling = None;
+    let mut workload_path = None;
+    let mut paths = Vec::new();
+
+    while let Some(arg) = args.next() {
+        if arg == "--sample" {
+            let Some(value) = args.next() else {
+                eprintln!("{HELP}");
+
+                return Ok(());
+            };
+
+            match value.parse::<Sampling>() {
+                Ok(value) => sampling = Some(value),
 
-    if args.len() != 2 {
+                Err(err) => {
+                    eprintln!("Invalid sample {value:?}: {err}");
+
+                    return Ok(());
+                }
+            }
+        }
+
+        else if arg == "--workload" {
+            let Some(value) = args.next() else {
+                eprintln!("{HELP}");
+
+                return Ok(());
+            };
+
+            workload_path = Some(PathBuf::from(value));
+        }
+
+        else {
+            paths.push(PathBuf::from(arg));
+        }
+    }
+
+    if paths.is_empty() {
         eprintln!("{HELP}");
 
         return Ok(());
     }
 
-    let path = PathBuf::from(&args[1]);
+    for path in paths.iter().chain(&workload_path) {
+        if !path.exists() {
+            eprintln!("File {path:?} doesn't exist");
 
-    if !path.exists() {
-        eprintln!("File {path:?} doesn't exist");
+            return Ok(());
+        }
+    }
 
-        return Ok(());
+    let database = rusqlite::Connection::open(&paths[0])?;
+
+    let mut schemas = vec![String::from("main"), String::from("temp")];
+
+    for path in &paths[1..] {
+        let stem = path.file_stem()
+            .map(|stem| stem.to_string_lossy())
+            .unwrap_or_default();
+
+        let mut schema = stem.chars()
+            .map(|char| if char.is_alphanumeric() { char } else { '_' })
+            .collect::<String>();
+
+        // Schema names are case insensitive.
+        let is_taken = |schema: &str| schemas.iter().any(|name| name.eq_ignore_ascii_case(schema));
+
+        if schema.is_empty() || is_taken(&schema) {
+            let mut suffix = schemas.len() - 1;
+
+            while is_taken(&format!("{schema}_{suffix}")) {
+                suffix += 1;
+            }
+
+            schema = format!("{schema}_{suffix}");
+        }
+
+        database.execute("ATTACH DATABASE ?1 AS ?2", (path.to_string_lossy(), &schema))?;
+
+        schemas.push(schema);
     }
 
-    let database = rusqlite::Connection::open(path)?;
+    // Query plans are made once the databases are attached
+    // so the queries can use any of them.
+    let workload = match workload_path {
+        Some(path) => Some(db_stats::query_workload(&database, &std::fs::read_to_string(path)?)?),
+        None => None
+    };
 
     let terminal = ratatui::init();
 
-    let result = tui::run(terminal, database);
+    let result = tui::run(terminal, database, sampling, workload);
 
     ratatui::restore();
 
@@ -1,5 +1,6 @@
 use std::io::Stdout;
 use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
 
 use spin::Mutex;
 
@@ -7,7 +8,8 @@ use ratatui::prelude::*;
 use ratatui::widgets::*;
 use ratatui::crossterm::event::{self, Event, KeyCode};
 
-use super::db_stats::Table;
+use super::db_stats::{Schema, Table, Column, Index, IndexColumn, ColumnKind, StorageClass, Redundancy, Sampling, Progress, Update, Workload, Journal};
+use super::db_stats;
 
 fn format_bytes(mut bytes: f64) -> String {
     if !bytes.is_normal() {
@@ -28,68 +30,598 @@ fn format_bytes(mut bytes: f64) -> String {
     format!("{bytes:.2} TB")
 }
 
-fn table_size(table: &Table) -> f64 {
-    (table.size + table.indexes.iter().map(|index| index.size).sum::<u64>()) as f64
+fn table_title(table: &Table) -> String {
+    match &table.module {
+        Some(module) => format!("Virtual table `{}` ({module})", table.name),
+        None if table.is_system => format!("System table `{}`", table.name),
+        None => format!("Table `{}`", table.name)
+    }
+}
+
+/// Format declared type of the column with its resolved affinity.
+fn format_type(column: &Column) -> String {
+    let mut format = if column.declared_type.eq_ignore_ascii_case(&column.affinity.to_string()) {
+        column.affinity.to_string()
+    } else if column.declared_type.is_empty() {
+        format!("(none) → {}", column.affinity)
+    } else {
+        format!("{} → {}", column.declared_type, column.affinity)
+    };
+
+    match column.kind {
+        ColumnKind::Ordinary => (),
+        ColumnKind::Hidden   => format.push_str(", hidden"),
+        ColumnKind::Virtual  => format.push_str(", virtual"),
+        ColumnKind::Stored   => format.push_str(", stored")
+    }
+
+    format
+}
+
+/// Format storage classes of the column values, highlighting
+/// values which don't match the column's type.
+fn format_storage(column: &Column, rows: u64) -> Line<'static> {
+    let mut spans = Vec::new();
+
+    for class in StorageClass::ALL {
+        let usage = column.storage[class as usize];
+
+        if usage.rows == 0 || class == StorageClass::Null {
+            continue;
+        }
+
+        if !spans.is_empty() {
+            spans.push(Span::from(", "));
+        }
+
+        let span = Span::from(format!(
+            "{}{class} {} ({})",
+            if column.margins.is_some() { "~" } else { "" },
+            format_fraction(usage.rows as f64 / rows as f64),
+            format_bytes(usage.size as f64)
+        ));
+
+        if column.affinity.is_expected(class) {
+            spans.push(span);
+        } else {
+            spans.push(span.red());
+        }
+    }
+
+    Line::from(spans)
+}
+
+/// Format bytes amount estimated with the given margin of error.
+fn format_estimated_bytes(bytes: u64, margin: Option<u64>) -> String {
+    match margin {
+        Some(margin) => format!("~{} ±{}", format_bytes(bytes as f64), format_bytes(margin as f64)),
+        None => format_bytes(bytes as f64)
+    }
+}
+
+fn format_fraction(fraction: f64) -> String {
+    format!("{:.2}%", if fraction.is_normal() { fraction * 100.0 } else { 0.0 })
+}
+
+fn format_index_column_name(column: &IndexColumn) -> String {
+    match (&column.name, &column.expression) {
+        (Some(name), _) => format!("`{name}`"),
+        (None, Some(expression)) => expression.clone(),
+        (None, None) if column.is_expression() => String::from("<expression>"),
+        (None, None) => String::from("rowid")
+    }
+}
+
+/// Format names of the tables used by a view or a trigger.
+fn format_tables(tables: &[String]) -> Span<'static> {
+    if tables.is_empty() {
+        return Span::from("nothing");
+    }
+
+    Span::from(tables.iter()
+        .map(|table| format!("`{table}`"))
+        .collect::<Vec<_>>()
+        .join(", "))
+}
+
+/// Format SQL statement of a schema object in a single line.
+fn format_sql(sql: &str) -> Line<'static> {
+    Line::from(format!("  {}", sql.split_whitespace().collect::<Vec<_>>().join(" "))).dark_gray()
+}
+
+/// Format key columns of the index with their sort order and collation.
+fn format_index_columns(index: &Index) -> String {
+    index.columns.iter()
+        .filter(|column| column.is_key)
+        .map(|column| {
+            let mut term = format_index_column_name(column);
+
+            if !column.collation.eq_ignore_ascii_case("binary") {
+                term.push_str(&format!(" COLLATE {}", column.collation));
+            }
+
+            if column.is_descending {
+                term.push_str(" DESC");
+            }
+
+            term
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Format amount of bytes each column uses in the index records.
+fn format_index_sizes(index: &Index, is_estimated: bool) -> String {
+    let prefix = if is_estimated { "~" } else { "" };
+
+    index.columns.iter()
+        .map(|column| {
+            let fraction = column.size as f64 / index.size as f64;
+
+            format!("{} {prefix}{} ({})", format_index_column_name(column), format_bytes(column.size as f64), format_fraction(fraction))
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Format sizes of the database journal files.
+fn format_journal(journal: &Journal) -> String {
+    let mut files = Vec::with_capacity(3);
+
+    if let Some(wal_size) = journal.wal_size {
+        files.push(format!("WAL {} ({} frames)", format_bytes(wal_size as f64), journal.wal_frames));
+    }
+
+    if let Some(shm_size) = journal.shm_size {
+        files.push(format!("shm {}", format_bytes(shm_size as f64)));
+    }
+
+    if let Some(journal_size) = journal.journal_size {
+        files.push(format!("rollback journal {}", format_bytes(journal_size as f64)));
+    }
+
+    if files.is_empty() {
+        return String::from("none");
+    }
+
+    files.join(", ")
+}
+
+/// Format amount of rows recorded by the last `ANALYZE` of the table.
+fn format_statistics(table: &Table) -> String {
+    match table.analyzed_rows {
+        Some(analyzed_rows) if table.is_analysis_stale() => format!("{analyzed_rows} rows analyzed, stale"),
+        Some(analyzed_rows) => format!("{analyzed_rows} rows analyzed"),
+        None => String::from("not analyzed")
+    }
+}
+
+/// Whether the index is redundant or unused by the workload queries.
+fn is_drop_candidate(schema: &str, index: &Index, table_indexes: &[Index], workload: Option<&Workload>) -> bool {
+    index.redundancy.is_some() || workload.is_some_and(|workload| workload.is_unused(schema, index, table_indexes))
+}
+
+fn table_size(table: &Table, show_system: bool) -> f64 {
+    let indexes_size = table.indexes.iter()
+        .filter(|index| show_system || !index.is_system)
+        .map(|index| index.size)
+        .sum::<u64>();
+
+    (table.size + indexes_size) as f64
+}
+
+/// Placeholder of the sizes of columns which values are not
+/// stored in the table records.
+const NOT_STORED: &str = "n/a (not stored)";
+
+/// Colors of the tables on the page map. Green is reserved
+/// for the selected table.
+const PAGE_MAP_COLORS: &[Color] = &[
+    Color::Blue,
+    Color::Yellow,
+    Color::Magenta,
+    Color::Cyan,
+    Color::Red,
+    Color::LightBlue,
+    Color::LightYellow,
+    Color::LightMagenta,
+    Color::LightCyan,
+    Color::LightRed
+];
+
+/// Render bordered table with the given header, rows and a bar
+/// of normalized fraction (`0.0..=1.0`) at the end of each row.
+fn render_table<const N: usize>(
+    frame: &mut Frame,
+    area: Rect,
+    title: &str,
+    header: [&str; N],
+    rows: Vec<([Line; N], f64)>,
+    bar_color: Color
+) {
+    let block_widget = Block::bordered().title_top(title);
+
+    let inner_area = block_widget.inner(area);
+
+    frame.render_widget(block_widget, area);
+
+    let mut widths = header.map(|name| name.len() as u16 + 2);
+
+    for (row, _) in &rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.width() as u16 + 2);
+        }
+    }
+
+    let constraints = widths.iter()
+        .map(|width| Constraint::Length(*width))
+        .chain([Constraint::Fill(1)])
+        .collect::<Vec<_>>();
+
+    let row_areas = Layout::vertical(vec![Constraint::Length(1); rows.len() + 1])
+        .split(inner_area);
+
+    let header_areas = Layout::horizontal(&constraints).split(row_areas[0]);
+
+    for (name, area) in header.into_iter().chain(["Bar"]).zip(header_areas.iter()) {
+        frame.render_widget(Span::from(name).underlined(), *area);
+    }
+
+    for ((row, norm_fraction), row_area) in rows.into_iter().zip(row_areas.iter().skip(1)) {
+        let cell_areas = Layout::horizontal(&constraints).split(*row_area);
+
+        for (cell, area) in row.into_iter().zip(cell_areas.iter()) {
+            frame.render_widget(cell, *area);
+        }
+
+        let [bar_area, _] = Layout::horizontal([
+            Constraint::Ratio((norm_fraction * u32::MAX as f64) as u32, u32::MAX),
+            Constraint::Fill(1)
+        ]).areas(cell_areas[N]);
+
+        frame.render_widget(Block::new().bg(bar_color), bar_area);
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 enum Page {
     TablesChart,
     TableDetails,
+    FileMap,
+    Objects,
+    Workload,
+    Header,
     VacuumQuestion,
-    VacuumProgress
+    VacuumProgress,
+    AnalyzeQuestion,
+    AnalyzeProgress
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 struct View {
     pub page: Page,
-    pub tables: Vec<Table>,
-    pub selected_table: usize
+    pub schemas: Vec<Schema>,
+    pub selected_schema: usize,
+    pub selected_table: usize,
+
+    /// Show SQLite internal tables and indexes.
+    pub show_system: bool,
+
+    /// Progress of the running database analysis.
+    pub progress: Option<Progress>,
+
+    /// Indexes usage by the queries given by the user.
+    pub workload: Option<Workload>,
+
+    /// Schema and table to run `ANALYZE` on, chosen
+    /// when the user was asked to confirm it.
+    pub analyze_target: Option<(String, String)>
 }
 
 impl View {
+    /// Apply update of the running database analysis, keeping
+    /// the same table selected.
+    pub fn apply(&mut self, update: Update) {
+        let selected_table = self.selected_table_name();
+
+        match update {
+            Update::Progress(progress) => self.progress = Some(progress),
+
+            Update::Schema(schema) => self.schemas.push(schema),
+
+            Update::Table { schema, table } => {
+                let Some(schema) = self.schemas.iter_mut().find(|item| item.name == schema) else {
+                    return;
+                };
+
+                match schema.tables.iter_mut().find(|item| item.name == table.name) {
+                    Some(pending_table) => *pending_table = table,
+                    None => schema.tables.push(table)
+                }
+
+                schema.tables.sort_by_key(|table| std::cmp::Reverse(table.size));
+            }
+        }
+
+        self.select_table(selected_table);
+    }
+
+    /// Replace schemas with the analysis results, keeping
+    /// the same table selected.
+    pub fn finish(&mut self, schemas: Vec<Schema>) {
+        let selected_table = self.selected_table_name();
+
+        self.schemas = schemas;
+        self.progress = None;
+
+        self.selected_schema = self.selected_schema.min(self.schemas.len().saturating_sub(1));
+
+        self.select_table(selected_table);
+    }
+
+    fn selected_table_name(&self) -> Option<String> {
+        if self.schemas.is_empty() {
+            return None;
+        }
+
+        self.tables().get(self.selected_table)
+            .map(|table| table.name.clone())
+    }
+
+    fn select_table(&mut self, name: Option<String>) {
+        if self.schemas.is_empty() {
+            return;
+        }
+
+        let tables = self.tables();
+
+        self.selected_table = match name {
+            Some(name) => tables.iter()
+                .position(|table| table.name == name)
+                .unwrap_or_default(),
+
+            None => self.selected_table.min(tables.len().saturating_sub(1))
+        };
+    }
+
+    #[inline]
+    pub fn schema(&self) -> &Schema {
+        &self.schemas[self.selected_schema]
+    }
+
+    /// Tables of the selected schema which are shown to the user.
+    pub fn tables(&self) -> Vec<&Table> {
+        self.schema().tables.iter()
+            .filter(|table| self.show_system || !table.is_system)
+            .collect()
+    }
+
     #[inline]
     pub fn table(&self) -> &Table {
-        &self.tables[self.selected_table]
+        self.tables()[self.selected_table]
+    }
+}
+
+/// Render the analysis progress.
+fn render_progress(frame: &mut Frame, progress: &Progress) {
+    let [_, message_area, _] = Layout::vertical([
+        Constraint::Fill(1),
+        Constraint::Length(10),
+        Constraint::Fill(1)
+    ]).areas(frame.area());
+
+    frame.render_widget(Block::new().on_yellow(), message_area);
+
+    let [_, message_area, _] = Layout::horizontal([
+        Constraint::Fill(1),
+        Constraint::Length(60),
+        Constraint::Fill(1)
+    ]).areas(message_area);
+
+    let [text_area, gauge_area, footer_area] = Layout::vertical([
+        Constraint::Length(6),
+        Constraint::Length(1),
+        Constraint::Length(3)
+    ]).areas(message_area);
+
+    let table = match &progress.table {
+        Some(table) if progress.rows > 0 => format!("Table `{table}`, {} of {} rows", progress.rows_done, progress.rows),
+        Some(table) => format!("Table `{table}`"),
+        None => String::from("Reading pages usage")
+    };
+
+    frame.render_widget(Text::from_iter([
+        Line::from(""),
+        Line::from("Analyzing database").bold(),
+        Line::from(""),
+        Line::from(format!("Schema `{}` ({} of {})", progress.schema, progress.schemas_done + 1, progress.schemas.max(1))),
+        Line::from(format!("Tables: {} of {}", progress.tables_done, progress.tables)),
+        Line::from(table)
+    ]), text_area);
+
+    frame.render_widget(Gauge::default().ratio(progress.fraction().clamp(0.0, 1.0)).blue().on_gray(), gauge_area);
+
+    frame.render_widget(Text::from_iter([
+        Line::from(""),
+        Line::from("Press Q to abort.").bold()
+    ]), footer_area);
+}
+
+/// Database analysis running on a background thread.
+struct Analysis {
+    worker: std::thread::JoinHandle<(rusqlite::Connection, anyhow::Result<Vec<Schema>>)>,
+    updates: std::sync::mpsc::Receiver<Update>,
+    aborted: Arc<AtomicBool>
+}
+
+impl Analysis {
+    fn start(database: rusqlite::Connection, sampling: Option<Sampling>) -> Self {
+        let aborted = Arc::new(AtomicBool::new(false));
+
+        // Interrupt running statements once the analysis is aborted.
+        database.progress_handler(1000, Some({
+            let aborted = aborted.clone();
+
+            move || aborted.load(Ordering::Relaxed)
+        }));
+
+        let (sender, updates) = std::sync::mpsc::channel();
+
+        let worker = std::thread::spawn(move || {
+            let schemas = super::db_stats::query_database(&database, sampling, &|update| {
+                let _ = sender.send(update);
+            });
+
+            (database, schemas)
+        });
+
+        Self {
+            worker,
+            updates,
+            aborted
+        }
+    }
+
+    /// Wait for the analysis to finish and get the database back.
+    fn finish(self) -> anyhow::Result<(rusqlite::Connection, anyhow::Result<Vec<Schema>>)> {
+        let (database, schemas) = self.worker.join()
+            .map_err(|_| anyhow::anyhow!("Database analysis thread panicked"))?;
+
+        database.progress_handler(0, None::<fn() -> bool>);
+
+        Ok((database, schemas))
+    }
+
+    fn abort(self) -> anyhow::Result<()> {
+        self.aborted.store(true, Ordering::Relaxed);
+
+        // Interrupted analysis fails which is expected.
+        let _ = self.finish()?;
+
+        Ok(())
     }
 }
 
-pub fn run(mut terminal: Terminal<CrosstermBackend<Stdout>>, database: rusqlite::Connection) -> anyhow::Result<()> {
+pub fn run(
+    mut terminal: Terminal<CrosstermBackend<Stdout>>,
+    database_connection: rusqlite::Connection,
+    sampling: Option<Sampling>,
+    workload: Option<Workload>
+) -> anyhow::Result<()> {
+    let mut database = None;
+    let mut analysis = Some(Analysis::start(database_connection, sampling));
+
     let view = Arc::new(Mutex::new(View {
         page: Page::TablesChart,
-        tables: super::db_stats::query_structure(&database)?,
-        selected_table: 0
+        schemas: Vec::new(),
+        selected_schema: 0,
+        selected_table: 0,
+        show_system: false,
+        progress: Some(Progress::default()),
+        workload,
+        analyze_target: None
     }));
 
-    let total_tables_size = view.lock().tables.iter().map(table_size).sum::<f64>();
-
     loop {
         let view_copy = view.clone();
 
         terminal.draw(move |frame| {
             let view = view_copy.lock();
 
+            // Nothing is known until pages usage of the first schema is read.
+            if view.schemas.is_empty() {
+                render_progress(frame, &view.progress.clone().unwrap_or_default());
+
+                return;
+            }
+
+            let schema = view.schema();
+            let tables = view.tables();
+
             let [area, footer_area] = Layout::vertical([
                 Constraint::Fill(1),
                 Constraint::Length(1)
             ]).areas(frame.area());
 
+            let footer_area = if let Some(progress) = &view.progress {
+                let [footer_area, progress_area] = Layout::horizontal([
+                    Constraint::Fill(1),
+                    Constraint::Length(40)
+                ]).areas(footer_area);
+
+                let label = match &progress.table {
+                    Some(table) => format!("Analyzing `{table}` {} / {}", progress.tables_done, progress.tables),
+                    None => format!("Analyzing `{}`", progress.schema)
+                };
+
+                frame.render_widget(Gauge::default().ratio(progress.fraction().clamp(0.0, 1.0)).label(label).blue().on_gray(), progress_area);
+
+                footer_area
+            } else {
+                footer_area
+            };
+
             frame.render_widget(Line::from_iter([
                 Span::from("Q").red(), Span::from("uit "),
                 Span::from("V").red(), Span::from("acuum "),
+                Span::from("A").red(), Span::from("nalyze "),
+                Span::from("M").red(), Span::from("ap "),
+                Span::from("O").red(), Span::from("bjects "),
+                Span::from("W").red(), Span::from("orkload "),
+                Span::from("H").red(), Span::from("eader "),
+                Span::from("S").red(), Span::from("ystem "),
+                Span::from("Tab").red(), Span::from(" Switch schema "),
                 Span::from("←→").red(), Span::from(" Select table "),
                 Span::from("↑↓").red(), Span::from(" Table details "),
                 Span::from("Enter").red(), Span::from(" Switch page ")
             ]), footer_area);
 
+            let area = if view.schemas.len() > 1 {
+                let [tabs_area, area] = Layout::vertical([
+                    Constraint::Length(1),
+                    Constraint::Fill(1)
+                ]).areas(area);
+
+                let tabs_widget = Tabs::new(view.schemas.iter().map(|schema| {
+                    format!("{} ({})", schema.name, format_bytes(schema.summary.footprint() as f64))
+                }));
+
+                frame.render_widget(tabs_widget.select(view.selected_schema).highlight_style(Style::new().green()), tabs_area);
+
+                area
+            } else {
+                area
+            };
+
+            if tables.is_empty() && matches!(view.page, Page::TablesChart | Page::TableDetails | Page::FileMap) {
+                let message_widget = Paragraph::new(format!("Schema `{}` has no tables", schema.name))
+                    .block(Block::bordered());
+
+                frame.render_widget(message_widget, area);
+
+                return;
+            }
+
             match view.page {
                 Page::TablesChart => {
-                    let [mut top_area, bottom_area] = Layout::vertical([
+                    let file_size = schema.summary.file_size as f64;
+
+                    // Percentages of the shown tables only.
+                    let total_size = tables.iter()
+                        .map(|table| table_size(table, view.show_system))
+                        .sum::<f64>();
+
+                    let [top_area, bottom_area] = Layout::vertical([
                         Constraint::Fill(1),
-                        Constraint::Length(5)
+                        Constraint::Length(12)
                     ]).areas(area);
 
-                    let bars_per_page = top_area.width as usize / 6;
+                    let [mut top_area, buckets_area] = Layout::horizontal([
+                        Constraint::Fill(1),
+                        Constraint::Length(19)
+                    ]).areas(top_area);
+
+                    let bars_per_page = (top_area.width as usize / 6).max(1);
 
                     let page = view.selected_table / bars_per_page;
 
@@ -102,14 +634,14 @@ pub fn run(mut terminal: Terminal<CrosstermBackend<Stdout>>, database: rusqlite:
 
                         top_area = remaining_top_area;
 
-                        let Some(table) = view.tables.get(i) else {
+                        let Some(table) = tables.get(i) else {
                             break;
                         };
 
-                        let table_size = table_size(table);
+                        let table_size = table_size(table, view.show_system);
 
-                        let real_table_fraction = table_size / total_tables_size;
-                        let norm_table_fraction = table_size.log2() / total_tables_size.log2();
+                        let real_table_fraction = table_size / total_size;
+                        let norm_table_fraction = table_size.log2() / total_size.log2();
 
                         let table_ratio = (norm_table_fraction * u32::MAX as f64) as u32;
 
@@ -158,132 +690,458 @@ pub fn run(mut terminal: Terminal<CrosstermBackend<Stdout>>, database: rusqlite:
                             Constraint::Ratio((table.size as f64 / table_size * u32::MAX as f64) as u32, u32::MAX)
                         ]).areas(inner_bar_area);
 
-                        let index_size_bar = Block::new().on_yellow();
-                        let table_size_bar = Block::new().on_blue();
+                        // Pending tables are shown with known sizes only.
+                        let (index_size_bar, table_size_bar) = if table.is_pending {
+                            (Block::new().on_gray(), Block::new().on_dark_gray())
+                        } else {
+                            (Block::new().on_yellow(), Block::new().on_blue())
+                        };
 
                         frame.render_widget(index_size_bar, index_bar_area);
                         frame.render_widget(table_size_bar, table_bar_area);
                     }
 
-                    let table_fraction = table_size(view.table()) / total_tables_size;
+                    // Internal objects, freelist and overhead buckets
+                    // as percentages of the whole database file.
 
-                    let bottom_widget = Paragraph::new(Text::from_iter([
+                    let buckets = [
+                        (schema.summary.system_size as f64, Color::Magenta),
+                        (schema.summary.free_size() as f64, Color::Gray),
+                        (schema.summary.overhead as f64, Color::Red)
+                    ];
+
+                    let [_, mut buckets_area] = Layout::horizontal([
+                        Constraint::Length(1),
+                        Constraint::Fill(1)
+                    ]).areas(buckets_area);
+
+                    for (bucket_size, color) in buckets {
+                        let [bar_area, _, remaining_buckets_area] = Layout::horizontal([
+                            Constraint::Length(5),
+                            Constraint::Length(1),
+                            Constraint::Fill(1)
+                        ]).areas(buckets_area);
+
+                        buckets_area = remaining_buckets_area;
+
+                        let real_bucket_fraction = bucket_size / file_size;
+                        let norm_bucket_fraction = if bucket_size > 1.0 { bucket_size.log2() / file_size.log2() } else { 0.0 };
+
+                        let [_, mut bar_area] = Layout::vertical([
+                            Constraint::Fill(1),
+                            Constraint::Ratio((norm_bucket_fraction * u32::MAX as f64) as u32, u32::MAX)
+                        ]).areas(bar_area);
+
+                        if bar_area.height < 3 {
+                            bar_area.y -= 3 - bar_area.height;
+                            bar_area.height = 3;
+                        }
+
+                        let bar_widget = Block::bordered()
+                            .borders(Borders::BOTTOM)
+                            .title_bottom(format!("{}%", (real_bucket_fraction * 100.0).round()));
+
+                        let inner_bar_area = bar_widget.inner(bar_area);
+
+                        frame.render_widget(bar_widget, bar_area);
+
+                        let [_, inner_bar_area, _] = Layout::horizontal([
+                            Constraint::Length(1),
+                            Constraint::Length(3),
+                            Constraint::Length(1)
+                        ]).areas(inner_bar_area);
+
+                        frame.render_widget(Block::new().bg(color), inner_bar_area);
+                    }
+
+                    let [table_area, database_area] = Layout::horizontal([
+                        Constraint::Fill(1),
+                        Constraint::Fill(1)
+                    ]).areas(bottom_area);
+
+                    let table_fraction = table_size(view.table(), view.show_system) / total_size;
+
+                    let indexes_size = table_size(view.table(), view.show_system) - view.table().size as f64;
+
+                    let table_widget = Paragraph::new(Text::from_iter([
                         format!("Table size  : {} ({:.2}% of total)", format_bytes(view.table().size as f64), table_fraction * 100.0),
-                        format!("Indexes size: {}", format_bytes(view.table().indexes.iter().map(|index| index.size as f64).sum::<f64>())),
-                        format!("Rows        : {}", view.table().rows)
+                        format!("Indexes size: {}", format_bytes(indexes_size)),
+                        format!("Payload     : {}", format_bytes(view.table().payload as f64)),
+                        format!("Unused      : {}", format_bytes(view.table().unused as f64)),
+                        match view.table().sampled_rows {
+                            _ if view.table().is_pending => String::from("Rows        : analyzing..."),
+                            Some(sampled_rows) if schema.summary.sampled_pages.is_some() => format!("Rows        : ~{} ({sampled_rows} sampled for columns)", view.table().rows),
+                            Some(sampled_rows) => format!("Rows        : {} ({sampled_rows} sampled for columns)", view.table().rows),
+                            None => format!("Rows        : {}", view.table().rows)
+                        },
+                        format!("Locality    : {:.2}% sequential", view.table().btree.locality.sequential() * 100.0)
+                    ].into_iter().map(Line::from).chain([
+                        if view.table().is_analysis_stale() {
+                            Line::from(format!("Statistics  : {}", format_statistics(view.table()))).red()
+                        } else {
+                            Line::from(format!("Statistics  : {}", format_statistics(view.table())))
+                        }
+                    ])));
+
+                    let table_widget = table_widget.block({
+                        Block::bordered()
+                            .title_top(table_title(view.table()))
+                    });
+
+                    frame.render_widget(table_widget, table_area);
+
+                    let database_widget = Paragraph::new(Text::from_iter([
+                        Line::from(if schema.summary.wal_pages > 0 {
+                            format!("File size   : {} (+{} pages in WAL)", format_bytes(schema.summary.file_size as f64), schema.summary.wal_pages)
+                        } else {
+                            format!("File size   : {}", format_bytes(schema.summary.file_size as f64))
+                        }),
+                        Line::from(match schema.summary.sampled_pages {
+                            Some(sampled_pages) => format!("Pages       : {} x {} (usage estimated from {sampled_pages} pages)", schema.summary.page_count, format_bytes(schema.summary.page_size as f64)),
+                            None => format!("Pages       : {} x {}", schema.summary.page_count, format_bytes(schema.summary.page_size as f64))
+                        }),
+                        Line::from(format!("Unused      : {}", format_bytes(schema.summary.unused as f64))),
+                        Line::from(format!("Locality    : {:.2}% sequential", schema.summary.locality.sequential() * 100.0)),
+                        Line::from(format!("Tables      : {} ({:.2}% of file)", format_bytes(total_size), total_size / file_size * 100.0)),
+                        Line::from_iter([
+                            Span::from("System").magenta(),
+                            Span::from(format!("      : {}", format_bytes(schema.summary.system_size as f64)))
+                        ]),
+                        Line::from_iter([
+                            Span::from("Free").gray(),
+                            Span::from(format!("        : {} ({} pages)", format_bytes(schema.summary.free_size() as f64), schema.summary.freelist_count))
+                        ]),
+                        Line::from_iter([
+                            Span::from("Overhead").red(),
+                            Span::from(format!("    : {}", format_bytes(schema.summary.overhead as f64)))
+                        ]),
+                        if schema.summary.is_wal_oversized() {
+                            Line::from(format!(
+                                "Journal     : {} (WAL is {:.1}x the database)",
+                                format_journal(&schema.summary.journal),
+                                schema.summary.journal.wal_size.unwrap_or_default() as f64 / file_size
+                            )).red()
+                        } else {
+                            Line::from(format!("Journal     : {}", format_journal(&schema.summary.journal)))
+                        },
+                        Line::from(format!("Footprint   : {} (file and journals)", format_bytes(schema.summary.footprint() as f64)))
                     ]));
 
-                    let bottom_widget = bottom_widget.block({
+                    let database_widget = database_widget.block({
                         Block::bordered()
-                            .title_top(format!("Table `{}`", view.table().name))
+                            .title_top(format!("Schema `{}`", schema.name))
                     });
 
-                    frame.render_widget(bottom_widget, bottom_area);
+                    frame.render_widget(database_widget, database_area);
                 }
 
                 Page::TableDetails => {
                     let table_borders_widget = Block::bordered()
-                        .title_top(format!("Table `{}`", view.table().name));
+                        .title_top(table_title(view.table()));
 
                     let table_details_area = table_borders_widget.inner(area);
 
                     frame.render_widget(table_borders_widget, area);
 
-                    // ===================== Columns table =====================
+                    // ===================== Pages summary =====================
 
-                    let total_columns_size = view.table().columns.iter()
-                        .map(|column| column.length as f64)
-                        .sum::<f64>();
+                    let btree = &view.table().btree;
 
-                    let (table_columns, sizes) = view.table().columns.iter()
-                        .map(|column| {
-                            let column_fraction = column.length as f64 / total_columns_size;
-                            let norm_column_fraction = (column.length as f64).log2() / total_columns_size.log2();
+                    let [table_pages_area, table_details_area] = Layout::vertical([
+                        Constraint::Length(6),
+                        Constraint::Fill(1)
+                    ]).areas(table_details_area);
 
-                            let name = column.name.as_str();
-                            let format = column.format.to_string();
-                            let size = format_bytes(column.length as f64);
-                            let fraction = format!("{:.2}%", if column_fraction.is_normal() { column_fraction * 100.0 } else { 0.0 });
+                    let table_pages_widget = Paragraph::new(Text::from_iter([
+                        format!(
+                            "{} interior, {} leaf, {} overflow ({} in overflow pages)",
+                            btree.interior_pages,
+                            btree.leaf_pages,
+                            btree.overflow_pages,
+                            format_bytes(btree.overflow_size as f64)
+                        ),
+                        format!(
+                            "Depth {}, fanout {:.1}, {:.1} cells per leaf, {:.2}% leaf fill",
+                            btree.depth,
+                            btree.fanout(),
+                            btree.cells_per_leaf(),
+                            btree.leaf_fill() * 100.0
+                        ),
+                        format!(
+                            "{:.2}% sequential, average jump of {:.1} pages",
+                            btree.locality.sequential() * 100.0,
+                            btree.locality.average_jump()
+                        )
+                    ].into_iter().map(Line::from).chain([
+                        if view.table().is_analysis_stale() {
+                            Line::from(format!("Statistics: {} ({} rows now)", format_statistics(view.table()), view.table().rows)).red()
+                        } else {
+                            Line::from(format!("Statistics: {}", format_statistics(view.table())))
+                        }
+                    ])));
 
-                            let sizes = (name.len(), format.len(), size.len(), fraction.len());
+                    frame.render_widget(table_pages_widget.block(Block::bordered().title_top("Pages")), table_pages_area);
 
-                            let row = (
-                                Line::from(name),
-                                Line::from(format),
-                                Line::from(size),
-                                Line::from(fraction),
-                                norm_column_fraction
-                            );
+                    // ===================== Views and triggers =====================
 
-                            (row, sizes)
+                    // Temporary triggers and views can use tables of the other
+                    // schemas, which they name qualified by the schema name.
+                    let name = view.table().name.as_str();
+
+                    let visible_names = view.schemas.iter()
+                        .filter_map(|other| {
+                            if other.name == schema.name {
+                                Some((other, name.to_string(), ""))
+                            } else if other.name == "temp" {
+                                Some((other, format!("{}.{name}", schema.name), "temp."))
+                            } else {
+                                None
+                            }
                         })
-                        .collect::<(Vec<_>, Vec<_>)>();
+                        .collect::<Vec<_>>();
 
-                    let sizes = sizes.into_iter().fold((4, 4, 9, 8), |acc, sizes| (
-                        acc.0.max(sizes.0),
-                        acc.1.max(sizes.1),
-                        acc.2.max(sizes.2),
-                        acc.3.max(sizes.2)
-                    ));
+                    let triggers = visible_names.iter()
+                        .flat_map(|(other, table, prefix)| {
+                            other.triggers.iter().map(move |trigger| (table.as_str(), format!("`{prefix}{}`", trigger.name), trigger))
+                        })
+                        .collect::<Vec<_>>();
 
-                    let [table_columns_area, area] = Layout::vertical([
-                        Constraint::Length(view.table().columns.len() as u16 + 3),
-                        Constraint::Fill(1)
-                    ]).areas(table_details_area);
+                    let views = visible_names.iter()
+                        .flat_map(|(other, table, prefix)| {
+                            other.views.iter().map(move |item| (table.as_str(), format!("`{prefix}{}`", item.name), item))
+                        })
+                        .collect::<Vec<_>>();
+
+                    let uses = |tables: &[String], table: &str| tables.iter().any(|item| item.eq_ignore_ascii_case(table));
+
+                    let dependants = [
+                        (
+                            "Triggers on the table",
+                            triggers.iter()
+                                .filter(|(table, _, trigger)| trigger.table.eq_ignore_ascii_case(table))
+                                .map(|(_, label, trigger)| match trigger.event {
+                                    Some(event) => format!("{label} ({event})"),
+                                    None => label.clone()
+                                })
+                                .collect::<Vec<_>>()
+                        ),
+                        (
+                            "Triggers writing the table",
+                            triggers.iter()
+                                .filter(|(table, _, trigger)| uses(&trigger.writes, table))
+                                .map(|(_, label, _)| label.clone())
+                                .collect()
+                        ),
+                        (
+                            "Triggers reading the table",
+                            triggers.iter()
+                                .filter(|(table, _, trigger)| uses(&trigger.reads, table))
+                                .map(|(_, label, _)| label.clone())
+                                .collect()
+                        ),
+                        (
+                            "Views reading the table",
+                            views.iter()
+                                .filter(|(table, _, item)| uses(&item.reads, table))
+                                .map(|(_, label, _)| label.clone())
+                                .collect()
+                        )
+                    ];
+
+                    let dependants = dependants.into_iter()
+                        .filter(|(_, objects)| !objects.is_empty())
+                        .map(|(title, objects)| Line::from(format!("{title}: {}", objects.join(", "))))
+                        .collect::<Vec<_>>();
+
+                    let table_details_area = if dependants.is_empty() {
+                        table_details_area
+                    } else {
+                        let [table_dependants_area, table_details_area] = Layout::vertical([
+                            Constraint::Length(dependants.len() as u16 + 2),
+                            Constraint::Fill(1)
+                        ]).areas(table_details_area);
 
-                    let table_columns_block_widget = Block::bordered().title_top("Columns");
+                        frame.render_widget(Paragraph::new(dependants).block(Block::bordered().title_top("Views and triggers")), table_dependants_area);
 
-                    let table_columns_inner_area = table_columns_block_widget.inner(table_columns_area);
+                        table_details_area
+                    };
 
-                    frame.render_widget(Block::bordered().title_top("Columns"), table_columns_area);
+                    // ===================== Shadow tables =====================
 
-                    let [table_columns_row_area, mut table_columns_inner_area] = Layout::vertical([
-                        Constraint::Length(1),
-                        Constraint::Fill(1)
-                    ]).areas(table_columns_inner_area);
+                    let area = if !view.table().shadows.is_empty() {
+                        let total_shadows_size = view.table().shadows.iter()
+                            .map(|shadow| table_size(shadow, true))
+                            .sum::<f64>();
 
-                    let [name_area, type_area, size_area, fraction_area, bar_area] = Layout::horizontal([
-                        Constraint::Length(sizes.0 as u16 + 2),
-                        Constraint::Length(sizes.1 as u16 + 2),
-                        Constraint::Length(sizes.2 as u16 + 2),
-                        Constraint::Length(sizes.3 as u16 + 2),
-                        Constraint::Fill(1)
-                    ]).areas(table_columns_row_area);
+                        let table_shadows = view.table().shadows.iter()
+                            .map(|shadow| {
+                                let shadow_size = table_size(shadow, true);
 
-                    frame.render_widget(Span::from("Name").underlined(), name_area);
-                    frame.render_widget(Span::from("Type").underlined(), type_area);
-                    frame.render_widget(Span::from("Disk size").underlined(), size_area);
-                    frame.render_widget(Span::from("Fraction").underlined(), fraction_area);
-                    frame.render_widget(Span::from("Bar").underlined(), bar_area);
+                                let shadow_fraction = shadow_size / total_shadows_size;
+                                let norm_shadow_fraction = shadow_size.log2() / total_shadows_size.log2();
 
-                    for (name_widget, type_widget, size_widget, fraction_widget, norm_column_fraction) in table_columns {
-                        let [table_columns_row_area, remaining_table_columns_inner_area] = Layout::vertical([
-                            Constraint::Length(1),
+                                let row = [
+                                    Line::from(shadow.name.as_str()),
+                                    Line::from(shadow.rows.to_string()),
+                                    Line::from(format_bytes(shadow.size as f64)),
+                                    Line::from(format_bytes(shadow.indexes.iter().map(|index| index.size as f64).sum::<f64>())),
+                                    Line::from(format_fraction(shadow_fraction))
+                                ];
+
+                                (row, norm_shadow_fraction)
+                            })
+                            .collect::<Vec<_>>();
+
+                        let [table_shadows_area, area] = Layout::vertical([
+                            Constraint::Length(view.table().shadows.len() as u16 + 3),
+                            Constraint::Fill(1)
+                        ]).areas(table_details_area);
+
+                        render_table(
+                            frame,
+                            table_shadows_area,
+                            "Shadow tables",
+                            ["Name", "Rows", "Disk size", "Indexes size", "Fraction"],
+                            table_shadows,
+                            Color::Blue
+                        );
+
+                        area
+                    } else {
+                        table_details_area
+                    };
+
+                    // ===================== Columns table =====================
+
+                    // Virtual table columns aren't records of its b-tree,
+                    // their values are measured in the shadow tables.
+                    let area = if view.table().module.is_some() && !view.table().columns.is_empty() {
+                        let table_columns = view.table().columns.iter()
+                            .map(|column| {
+                                let row = [
+                                    Line::from(column.name.as_str()),
+                                    Line::from(format_type(column)),
+                                    Line::from(NOT_STORED).dark_gray(),
+                                    if column.kind == ColumnKind::Hidden {
+                                        Line::from("provided by the module").dark_gray()
+                                    } else {
+                                        Line::from("stored in shadow tables").dark_gray()
+                                    }
+                                ];
+
+                                (row, 0.0)
+                            })
+                            .collect::<Vec<_>>();
+
+                        let [table_columns_area, area] = Layout::vertical([
+                            Constraint::Length(view.table().columns.len() as u16 + 3),
                             Constraint::Fill(1)
-                        ]).areas(table_columns_inner_area);
+                        ]).areas(area);
 
-                        table_columns_inner_area = remaining_table_columns_inner_area;
+                        render_table(
+                            frame,
+                            table_columns_area,
+                            "Columns",
+                            ["Name", "Type", "Disk size", "Storage"],
+                            table_columns,
+                            Color::Blue
+                        );
+
+                        area
+                    } else if !view.table().columns.is_empty() {
+                        let total_columns_size = view.table().columns.iter()
+                            .map(|column| column.size as f64)
+                            .sum::<f64>();
 
-                        let [name_area, type_area, size_area, fraction_area, bar_area] = Layout::horizontal([
-                            Constraint::Length(sizes.0 as u16 + 2),
-                            Constraint::Length(sizes.1 as u16 + 2),
-                            Constraint::Length(sizes.2 as u16 + 2),
-                            Constraint::Length(sizes.3 as u16 + 2),
+                        let table_columns = view.table().columns.iter()
+                            .map(|column| {
+                                let column_fraction = column.size as f64 / total_columns_size;
+                                let norm_column_fraction = (column.size as f64).log2() / total_columns_size.log2();
+
+                                let is_stored = view.table().stores_column(column);
+
+                                let row = [
+                                    if column.mismatched_rows() > 0 {
+                                        Line::from(column.name.as_str()).red()
+                                    } else {
+                                        Line::from(column.name.as_str())
+                                    },
+                                    Line::from(format_type(column)),
+                                    if is_stored {
+                                        Line::from(format_estimated_bytes(column.size, column.margins.map(|margins| margins.size)))
+                                    } else {
+                                        Line::from(NOT_STORED).dark_gray()
+                                    },
+                                    Line::from(format_estimated_bytes(column.logical_size, column.margins.map(|margins| margins.logical_size))),
+                                    if is_stored {
+                                        Line::from(format_estimated_bytes(column.overflow_size, column.margins.map(|margins| margins.overflow_size)))
+                                    } else {
+                                        Line::from(NOT_STORED).dark_gray()
+                                    },
+                                    Line::from(match column.margins {
+                                        Some(margins) => format!("~{} ±{} ({})", column.nulls, margins.nulls, format_fraction(column.nulls as f64 / view.table().rows as f64)),
+                                        None => format!("{} ({})", column.nulls, format_fraction(column.nulls as f64 / view.table().rows as f64))
+                                    }),
+                                    Line::from(format!("{}{}", if column.is_distinct_estimated { "~" } else { "" }, column.distinct)),
+                                    if is_stored {
+                                        Line::from(format!(
+                                            "{}{} / {:.1} / {}",
+                                            if column.margins.is_some() { "~" } else { "" },
+                                            column.min_value_size,
+                                            column.average_value_size(view.table().rows),
+                                            column.max_value_size
+                                        ))
+                                    } else {
+                                        Line::from(NOT_STORED).dark_gray()
+                                    },
+                                    if is_stored {
+                                        format_storage(column, view.table().rows)
+                                    } else {
+                                        Line::from(NOT_STORED).dark_gray()
+                                    },
+                                    Line::from(format_fraction(column_fraction))
+                                ];
+
+                                (row, norm_column_fraction)
+                            })
+                            .collect::<Vec<_>>();
+
+                        let [table_columns_area, area] = Layout::vertical([
+                            Constraint::Length(view.table().columns.len() as u16 + 3),
                             Constraint::Fill(1)
-                        ]).areas(table_columns_row_area);
+                        ]).areas(area);
 
-                        frame.render_widget(name_widget, name_area);
-                        frame.render_widget(type_widget, type_area);
-                        frame.render_widget(size_widget, size_area);
-                        frame.render_widget(fraction_widget, fraction_area);
+                        let title = match view.table().sampled_rows {
+                            Some(sampled_rows) if view.table().is_sample_approximate => format!("Columns (estimated from {sampled_rows} sampled rows, approximate ± as the rowids are sparse)"),
+                            Some(sampled_rows) => format!("Columns (estimated from {sampled_rows} sampled rows, ± for 95% confidence)"),
+                            None => String::from("Columns")
+                        };
 
-                        let [bar_area, _] = Layout::horizontal([
-                            Constraint::Ratio((norm_column_fraction * u32::MAX as f64) as u32, u32::MAX),
+                        render_table(
+                            frame,
+                            table_columns_area,
+                            &title,
+                            ["Name", "Type", "Disk size", "Logical size", "Overflow", "NULLs", "Distinct", "Min/avg/max", "Storage", "Fraction"],
+                            table_columns,
+                            Color::Blue
+                        );
+
+                        area
+                    } else if view.table().is_pending {
+                        let [table_columns_area, area] = Layout::vertical([
+                            Constraint::Length(3),
                             Constraint::Fill(1)
-                        ]).areas(bar_area);
+                        ]).areas(area);
 
-                        frame.render_widget(Block::new().on_blue(), bar_area);
-                    }
+                        let message_widget = Paragraph::new("Columns are being analyzed...")
+                            .block(Block::bordered().title_top("Columns"));
+
+                        frame.render_widget(message_widget, table_columns_area);
+
+                        area
+                    } else {
+                        area
+                    };
 
                     // ===================== Indexes table =====================
 
@@ -292,95 +1150,441 @@ pub fn run(mut terminal: Terminal<CrosstermBackend<Stdout>>, database: rusqlite:
                             .map(|index| index.size as f64)
                             .sum::<f64>();
 
-                        let (table_indexes, sizes) = view.table().indexes.iter()
+                        let table_indexes = view.table().indexes.iter()
                             .map(|index| {
                                 let index_fraction = index.size as f64 / total_indexes_size;
                                 let norm_index_fraction = (index.size as f64).log2() / total_indexes_size.log2();
 
-                                let name = index.name.as_str();
-                                let size = format_bytes(index.size as f64);
-                                let fraction = format!("{:.2}%", if index_fraction.is_normal() { index_fraction * 100.0 } else { 0.0 });
+                                let row = [
+                                    if index.is_system {
+                                        Line::from(index.name.as_str()).magenta()
+                                    } else {
+                                        Line::from(index.name.as_str())
+                                    },
+                                    Line::from(format_bytes(index.size as f64)),
+                                    Line::from(match &index.statistics {
+                                        Some(statistics) => statistics.rows_per_key.iter()
+                                            .map(|rows| rows.to_string())
+                                            .collect::<Vec<_>>()
+                                            .join(" / "),
+
+                                        None => String::new()
+                                    }),
+                                    Line::from(index.btree.depth.to_string()),
+                                    Line::from(format!("{}/{}", index.btree.interior_pages, index.btree.leaf_pages)),
+                                    Line::from(format!("{:.1}", index.btree.fanout())),
+                                    Line::from(format!("{:.1}", index.btree.cells_per_leaf())),
+                                    Line::from(format_fraction(index.btree.leaf_fill())),
+                                    Line::from(format_fraction(index.btree.locality.sequential())),
+                                    Line::from(format_fraction(index_fraction)),
+                                    match &index.redundancy {
+                                        Some(Redundancy::Duplicate(other)) => Line::from(format!("duplicate of `{other}`")).red(),
+                                        Some(Redundancy::Prefix(other)) => Line::from(format!("prefix of `{other}`")).red(),
+                                        Some(Redundancy::PrimaryKey) => Line::from("prefix of primary key").red(),
+
+                                        None if is_drop_candidate(&view.schema().name, index, &view.table().indexes, view.workload.as_ref()) => Line::from("unused by workload").red(),
+                                        None => Line::default()
+                                    }
+                                ];
+
+                                (row, norm_index_fraction)
+                            })
+                            .collect::<Vec<_>>();
 
-                                let sizes = (name.len(), size.len(), fraction.len());
+                        let [table_indexes_area, index_definitions_area, _] = Layout::vertical([
+                            Constraint::Length(view.table().indexes.len() as u16 + 3),
+                            Constraint::Length(view.table().indexes.len() as u16 * 3 + 2),
+                            Constraint::Fill(1)
+                        ]).areas(area);
 
-                                let row = (
-                                    Line::from(name),
-                                    Line::from(size),
-                                    Line::from(fraction),
-                                    norm_index_fraction
-                                );
+                        let reclaimable_size = view.table().indexes.iter()
+                            .filter(|index| is_drop_candidate(&view.schema().name, index, &view.table().indexes, view.workload.as_ref()))
+                            .map(|index| index.size as f64)
+                            .sum::<f64>();
 
-                                (row, sizes)
+                        let title = if reclaimable_size > 0.0 {
+                            format!("Indexes ({} can be reclaimed by dropping the candidates)", format_bytes(reclaimable_size))
+                        } else {
+                            String::from("Indexes")
+                        };
+
+                        render_table(
+                            frame,
+                            table_indexes_area,
+                            &title,
+                            ["Name", "Disk size", "Rows per key", "Depth", "Interior/leaf", "Fanout", "Cells/leaf", "Leaf fill", "Sequential", "Fraction", "Drop candidate"],
+                            table_indexes,
+                            Color::Yellow
+                        );
+
+                        let index_definitions = view.table().indexes.iter()
+                            .flat_map(|index| {
+                                let mut definition = vec![
+                                    if index.is_system {
+                                        Span::from(index.name.as_str()).magenta()
+                                    } else {
+                                        Span::from(index.name.as_str())
+                                    },
+                                    Span::from(format!(" ({})", format_index_columns(index))),
+                                    Span::from(format!(" origin {}", index.origin.code())).dark_gray()
+                                ];
+
+                                if index.is_unique {
+                                    definition.push(Span::from(" UNIQUE").yellow());
+                                }
+
+                                if let Some(condition) = &index.condition {
+                                    definition.push(Span::from(format!(" WHERE {condition}")).cyan());
+                                } else if index.is_partial {
+                                    definition.push(Span::from(" partial").cyan());
+                                }
+
+                                if let Some(statistics) = &index.statistics {
+                                    definition.push(Span::from(format!(" {} rows analyzed", statistics.rows)).dark_gray());
+
+                                    if statistics.samples > 0 {
+                                        definition.push(Span::from(format!(", {} samples", statistics.samples)).dark_gray());
+                                    }
+                                }
+
+                                let sql = match &index.sql {
+                                    Some(sql) => Span::from(format!("  {}", sql.split_whitespace().collect::<Vec<_>>().join(" "))).dark_gray(),
+                                    None => Span::from("  Created automatically for the table constraint").dark_gray()
+                                };
+
+                                let sizes = if view.table().is_pending {
+                                    Span::from("  Columns sizes are being analyzed...").dark_gray()
+                                } else {
+                                    Span::from(format!("  {}", format_index_sizes(index, view.table().sampled_rows.is_some())))
+                                };
+
+                                [Line::from(definition), Line::from(sql), Line::from(sizes)]
                             })
-                            .collect::<(Vec<_>, Vec<_>)>();
+                            .collect::<Vec<_>>();
 
-                        let sizes = sizes.into_iter().fold((4, 9, 8), |acc, sizes| (
-                            acc.0.max(sizes.0),
-                            acc.1.max(sizes.1),
-                            acc.2.max(sizes.2)
-                        ));
+                        let index_definitions_widget = Paragraph::new(index_definitions)
+                            .block(Block::bordered().title_top("Index definitions"));
 
-                        let [table_indexes_area, _] = Layout::vertical([
-                            Constraint::Length(view.table().indexes.len() as u16 + 3),
-                            Constraint::Fill(1)
-                        ]).areas(area);
+                        frame.render_widget(index_definitions_widget, index_definitions_area);
+                    }
+                }
 
-                        let table_indexes_block_widget = Block::bordered().title_top("Columns");
+                Page::FileMap => {
+                    if schema.summary.sampled_pages.is_some() {
+                        let message_widget = Paragraph::new("Owners of the pages are not known when sampling: start vinolite without `--sample` option to read every page")
+                            .block(Block::bordered().title_top("Page map"));
 
-                        let table_indexes_inner_area = table_indexes_block_widget.inner(table_indexes_area);
+                        frame.render_widget(message_widget, area);
 
-                        frame.render_widget(Block::bordered().title_top("Indexes"), table_indexes_area);
+                        return;
+                    }
 
-                        let [table_indexes_row_area, mut table_indexes_inner_area] = Layout::vertical([
-                            Constraint::Length(1),
-                            Constraint::Fill(1)
-                        ]).areas(table_indexes_inner_area);
+                    let page_map = &schema.summary.page_map;
 
-                        let [name_area, size_area, fraction_area, bar_area] = Layout::horizontal([
-                            Constraint::Length(sizes.0 as u16 + 2),
-                            Constraint::Length(sizes.1 as u16 + 2),
-                            Constraint::Length(sizes.2 as u16 + 2),
-                            Constraint::Fill(1)
-                        ]).areas(table_indexes_row_area);
+                    // Index of the table owning each object of the page map.
+                    let objects_tables = page_map.objects.iter()
+                        .map(|object| {
+                            tables.iter().position(|table| {
+                                &table.name == object ||
+                                table.indexes.iter().any(|index| &index.name == object) ||
+                                table.shadows.iter().any(|shadow| &shadow.name == object)
+                            })
+                        })
+                        .collect::<Vec<_>>();
 
-                        frame.render_widget(Span::from("Name").underlined(), name_area);
-                        frame.render_widget(Span::from("Disk size").underlined(), size_area);
-                        frame.render_widget(Span::from("Fraction").underlined(), fraction_area);
-                        frame.render_widget(Span::from("Bar").underlined(), bar_area);
+                    let [map_area, info_area] = Layout::vertical([
+                        Constraint::Fill(1),
+                        Constraint::Length(4)
+                    ]).areas(area);
 
-                        for (name_widget, size_widget, fraction_widget, norm_index_fraction) in table_indexes {
-                            let [table_indexes_row_area, remaining_table_indexes_inner_area] = Layout::vertical([
-                                Constraint::Length(1),
-                                Constraint::Fill(1)
-                            ]).areas(table_indexes_inner_area);
+                    let map_block_widget = Block::bordered().title_top("Page map");
 
-                            table_indexes_inner_area = remaining_table_indexes_inner_area;
+                    let map_inner_area = map_block_widget.inner(map_area);
 
-                            let [name_area, size_area, fraction_area, bar_area] = Layout::horizontal([
-                                Constraint::Length(sizes.0 as u16 + 2),
-                                Constraint::Length(sizes.1 as u16 + 2),
-                                Constraint::Length(sizes.2 as u16 + 2),
-                                Constraint::Fill(1)
-                            ]).areas(table_indexes_row_area);
+                    frame.render_widget(map_block_widget, map_area);
 
-                            frame.render_widget(name_widget, name_area);
-                            frame.render_widget(size_widget, size_area);
-                            frame.render_widget(fraction_widget, fraction_area);
+                    let cells = (map_inner_area.width as u64 * map_inner_area.height as u64).max(1);
+                    let pages_per_cell = schema.summary.page_count.div_ceil(cells).max(1);
 
-                            let [bar_area, _] = Layout::horizontal([
-                                Constraint::Ratio((norm_index_fraction * u32::MAX as f64) as u32, u32::MAX),
-                                Constraint::Fill(1)
-                            ]).areas(bar_area);
+                    let mut lines = Vec::with_capacity(map_inner_area.height as usize);
+                    let mut line = Vec::with_capacity(map_inner_area.width as usize);
 
-                            frame.render_widget(Block::new().on_yellow(), bar_area);
+                    let mut runs = page_map.runs.iter().peekable();
+
+                    let mut selected_pages = 0;
+
+                    for first_page in (1..=schema.summary.page_count).step_by(pages_per_cell as usize) {
+                        let last_page = (first_page + pages_per_cell - 1).min(schema.summary.page_count);
+
+                        // Amount of pages of each table in the cell. `None` is used
+                        // for free pages and `Some(None)` for objects without table.
+                        let mut owners = Vec::<(Option<Option<usize>>, u64)>::new();
+
+                        let mut is_selected = false;
+
+                        while let Some(run) = runs.peek() {
+                            let run_last_page = run.first_page + run.pages - 1;
+
+                            let pages = run_last_page.min(last_page) + 1 - run.first_page.max(first_page);
+
+                            let owner = run.owner.map(|owner| objects_tables[owner]);
+
+                            if owner == Some(Some(view.selected_table)) {
+                                is_selected = true;
+                                selected_pages += pages;
+                            }
+
+                            match owners.iter_mut().find(|(cell_owner, _)| *cell_owner == owner) {
+                                Some((_, owner_pages)) => *owner_pages += pages,
+                                None => owners.push((owner, pages))
+                            }
+
+                            if run_last_page > last_page {
+                                break;
+                            }
+
+                            runs.next();
                         }
+
+                        let owner = owners.iter()
+                            .max_by_key(|(_, pages)| *pages)
+                            .and_then(|(owner, _)| *owner);
+
+                        let span = match owner {
+                            _ if is_selected => Span::from("█").green(),
+
+                            Some(Some(table)) => Span::from("█").fg(PAGE_MAP_COLORS[table % PAGE_MAP_COLORS.len()]),
+                            Some(None) => Span::from("█").gray(),
+
+                            None => Span::from("░").dark_gray()
+                        };
+
+                        line.push(span);
+
+                        if line.len() == map_inner_area.width as usize {
+                            lines.push(Line::from(std::mem::take(&mut line)));
+                        }
+                    }
+
+                    if !line.is_empty() {
+                        lines.push(Line::from(line));
                     }
+
+                    frame.render_widget(Text::from(lines), map_inner_area);
+
+                    let free_pages = page_map.runs.iter()
+                        .filter(|run| run.owner.is_none())
+                        .map(|run| run.pages)
+                        .sum::<u64>();
+
+                    let info_widget = Paragraph::new(Text::from_iter([
+                        Line::from_iter([
+                            Span::from("█").green(),
+                            Span::from(format!(
+                                " Table `{}`: {} pages ({:.2}% of file)",
+                                view.table().name,
+                                selected_pages,
+                                selected_pages as f64 / schema.summary.page_count as f64 * 100.0
+                            ))
+                        ]),
+                        Line::from_iter([
+                            Span::from("░").dark_gray(),
+                            Span::from(format!(" Free: {free_pages} pages ({} in freelist)", schema.summary.freelist_count)),
+                            Span::from(format!(", 1 cell = {pages_per_cell} pages"))
+                        ])
+                    ]));
+
+                    frame.render_widget(info_widget.block(Block::bordered()), info_area);
+                }
+
+                Page::Objects => {
+                    let [views_area, triggers_area] = Layout::vertical([
+                        Constraint::Length(schema.views.len().max(1) as u16 * 2 + 2),
+                        Constraint::Fill(1)
+                    ]).areas(area);
+
+                    let views = schema.views.iter()
+                        .flat_map(|item| {
+                            let definition = Line::from_iter([
+                                Span::from(item.name.as_str()).bold(),
+                                Span::from(" reads "),
+                                format_tables(&item.reads).cyan()
+                            ]);
+
+                            [definition, format_sql(&item.sql)]
+                        })
+                        .collect::<Vec<_>>();
+
+                    let triggers = schema.triggers.iter()
+                        .flat_map(|trigger| {
+                            let event = match trigger.event {
+                                Some(event) => Span::from(format!(" {event} ON `{}`", trigger.table)).yellow(),
+                                None => Span::from(format!(" ON `{}`", trigger.table)).yellow()
+                            };
+
+                            let definition = Line::from_iter([
+                                Span::from(trigger.name.as_str()).bold(),
+                                event,
+                                Span::from(" reads "),
+                                format_tables(&trigger.reads).cyan(),
+                                Span::from(" writes "),
+                                format_tables(&trigger.writes).red()
+                            ]);
+
+                            [definition, format_sql(&trigger.sql)]
+                        })
+                        .collect::<Vec<_>>();
+
+                    frame.render_widget(Paragraph::new(views).block(Block::bordered().title_top(format!("Views ({})", schema.views.len()))), views_area);
+                    frame.render_widget(Paragraph::new(triggers).block(Block::bordered().title_top(format!("Triggers ({})", schema.triggers.len()))), triggers_area);
+                }
+
+                Page::Workload => {
+                    let Some(workload) = &view.workload else {
+                        let message_widget = Paragraph::new("Start vinolite with `--workload <path>` option to find indexes unused by the SQL queries from the file")
+                            .block(Block::bordered().title_top("Workload"));
+
+                        frame.render_widget(message_widget, area);
+
+                        return;
+                    };
+
+                    // Unused indexes of the selected schema, including indexes of the shadow tables.
+                    let unused_indexes = schema.tables.iter()
+                        .flat_map(|table| {
+                            std::iter::once(table).chain(&table.shadows)
+                                .flat_map(|table| {
+                                    table.indexes.iter()
+                                        .filter(|index| workload.is_unused(&schema.name, index, &table.indexes))
+                                        .map(move |index| (table, index))
+                                })
+                        })
+                        .collect::<Vec<_>>();
+
+                    let reclaimable_size = unused_indexes.iter()
+                        .map(|(_, index)| index.size as f64)
+                        .sum::<f64>();
+
+                    let [info_area, indexes_area, queries_area] = Layout::vertical([
+                        Constraint::Length(5),
+                        Constraint::Length(unused_indexes.len().max(1) as u16 + 3),
+                        Constraint::Fill(1)
+                    ]).areas(area);
+
+                    let info_widget = Paragraph::new(Text::from_iter([
+                        Line::from(format!("Queries        : {} ({} failed)", workload.queries.len(), workload.failed())),
+                        Line::from(format!("Unused indexes : {}", unused_indexes.len())),
+                        Line::from(format!("Reclaimable    : {}", format_bytes(reclaimable_size)))
+                    ]));
+
+                    frame.render_widget(info_widget.block(Block::bordered().title_top(format!("Workload of schema `{}`", schema.name))), info_area);
+
+                    let unused_indexes = unused_indexes.iter()
+                        .map(|(table, index)| {
+                            let index_fraction = index.size as f64 / reclaimable_size;
+                            let norm_index_fraction = (index.size as f64).log2() / reclaimable_size.log2();
+
+                            let row = [
+                                Line::from(table.name.as_str()),
+                                Line::from(index.name.as_str()),
+                                Line::from(format_bytes(index.size as f64)),
+                                Line::from(format_fraction(index_fraction))
+                            ];
+
+                            (row, norm_index_fraction)
+                        })
+                        .collect::<Vec<_>>();
+
+                    render_table(
+                        frame,
+                        indexes_area,
+                        "Indexes unused by any query (drop candidates)",
+                        ["Table", "Index", "Disk size", "Fraction"],
+                        unused_indexes,
+                        Color::Red
+                    );
+
+                    let queries = workload.queries.iter()
+                        .map(|query| {
+                            // Show each query in a single line.
+                            let sql = query.sql.split_whitespace()
+                                .collect::<Vec<_>>()
+                                .join(" ");
+
+                            let usage = match &query.error {
+                                Some(err) => Span::from(err.as_str()).red(),
+                                None if query.indexes.is_empty() => Span::from("no indexes").dark_gray(),
+                                None => Span::from(query.indexes.join(", ")).green()
+                            };
+
+                            Line::from_iter([usage, Span::from(" ← "), Span::from(sql)])
+                        })
+                        .collect::<Vec<_>>();
+
+                    frame.render_widget(Paragraph::new(queries).block(Block::bordered().title_top("Queries")), queries_area);
+                }
+
+                Page::Header => {
+                    let Some(header) = &schema.summary.header else {
+                        let message_widget = Paragraph::new(format!("Schema `{}` is stored in memory and has no file header", schema.name))
+                            .block(Block::bordered().title_top("Header"));
+
+                        frame.render_widget(message_widget, area);
+
+                        return;
+                    };
+
+                    let journal_mode = |version: u8| match version {
+                        1 => "rollback journal",
+                        2 => "WAL",
+                        _ => "unknown"
+                    };
+
+                    // The database file can be truncated or extended
+                    // by other tools without updating the header.
+                    let file_pages = schema.summary.file_size / header.page_size;
+
+                    let database_size = format!("Database size    : {} pages in header, {file_pages} pages in file", header.page_count);
+
+                    let database_size = if !header.is_page_count_valid() {
+                        Line::from(format!("{database_size} (header size is outdated)")).red()
+                    } else if header.page_count as u64 != file_pages {
+                        Line::from(database_size).red()
+                    } else {
+                        Line::from(database_size)
+                    };
+
+                    let auto_vacuum = match (header.is_auto_vacuum(), header.incremental_vacuum) {
+                        (false, _) => String::from("none"),
+                        (true, false) => format!("full (largest root page {})", header.largest_root_page),
+                        (true, true) => format!("incremental (largest root page {})", header.largest_root_page)
+                    };
+
+                    let header_widget = Paragraph::new(Text::from_iter([
+                        Line::from(format!("Page size        : {} ({} bytes)", format_bytes(header.page_size as f64), header.page_size)),
+                        Line::from(format!("Write version    : {} ({})", header.write_version, journal_mode(header.write_version))),
+                        Line::from(format!("Read version     : {} ({})", header.read_version, journal_mode(header.read_version))),
+                        Line::from(format!("Reserved bytes   : {} per page", header.reserved_bytes)),
+                        Line::from(format!("Change counter   : {}", header.change_counter)),
+                        database_size,
+                        Line::from(format!("Freelist         : {} pages, first trunk page {}", header.freelist_count, header.freelist_trunk_page)),
+                        Line::from(format!("Schema cookie    : {}", header.schema_cookie)),
+                        Line::from(format!("Schema format    : {}", header.schema_format)),
+                        Line::from(format!("Default cache    : {} pages", header.default_cache_size)),
+                        Line::from(format!("Text encoding    : {}", header.encoding())),
+                        Line::from(format!("User version     : {}", header.user_version)),
+                        Line::from(format!("Application id   : {} ({:#010x})", header.application_id, header.application_id)),
+                        Line::from(format!("Auto vacuum      : {auto_vacuum}")),
+                        Line::from(format!("SQLite version   : {} (at change {})", header.version(), header.version_valid_for))
+                    ]));
+
+                    frame.render_widget(header_widget.block(Block::bordered().title_top(format!("Header of schema `{}`", schema.name))), area);
                 }
 
                 Page::VacuumQuestion => {
                     let [_, message_area, _] = Layout::vertical([
                         Constraint::Fill(1),
-                        Constraint::Length(11),
+                        Constraint::Length(14),
                         Constraint::Fill(1)
                     ]).areas(area);
 
@@ -402,11 +1606,79 @@ pub fn run(mut terminal: Terminal<CrosstermBackend<Stdout>>, database: rusqlite:
                         Line::from("This operation can take some time."),
                         Line::from("Make a backup prior that."),
                         Line::from(""),
+                        Line::from(format!("Free pages: {}", format_bytes(schema.summary.free_size() as f64))),
+                        Line::from(format!("Sequential pages: {:.2}%", schema.summary.locality.sequential() * 100.0)),
+                        Line::from(""),
+                        Line::from("Press enter to continue.").bold(),
+                        Line::from("")
+                    ]), message_area);
+                }
+
+                Page::AnalyzeQuestion => {
+                    let Some((target_schema, target_table)) = &view.analyze_target else {
+                        return;
+                    };
+
+                    let table = view.schemas.iter()
+                        .find(|schema| &schema.name == target_schema)
+                        .and_then(|schema| schema.tables.iter().find(|table| &table.name == target_table));
+
+                    let [_, message_area, _] = Layout::vertical([
+                        Constraint::Fill(1),
+                        Constraint::Length(14),
+                        Constraint::Fill(1)
+                    ]).areas(area);
+
+                    frame.render_widget(Block::new().on_yellow(), message_area);
+
+                    let [_, message_area, _] = Layout::horizontal([
+                        Constraint::Fill(1),
+                        Constraint::Length(40),
+                        Constraint::Fill(1)
+                    ]).areas(message_area);
+
+                    frame.render_widget(Text::from_iter([
+                        Line::from(""),
+                        Line::from(format!("Analyze `{target_schema}`.`{target_table}`")).bold(),
+                        Line::from(""),
+                        Line::from("Gather the table and its indexes"),
+                        Line::from("statistics used by the query planner."),
+                        Line::from(""),
+                        Line::from("This operation can take some time."),
+                        Line::from("The statistics are written to the file."),
+                        Line::from(""),
+                        Line::from(format!("Rows: {}", table.map(|table| table.rows).unwrap_or_default())),
+                        Line::from(format!("Statistics: {}", table.map(format_statistics).unwrap_or_default())),
+                        Line::from(""),
                         Line::from("Press enter to continue.").bold(),
                         Line::from("")
                     ]), message_area);
                 }
 
+                Page::AnalyzeProgress => {
+                    let [_, message_area, _] = Layout::vertical([
+                        Constraint::Fill(1),
+                        Constraint::Length(5),
+                        Constraint::Fill(1)
+                    ]).areas(area);
+
+                    frame.render_widget(Block::new().on_yellow(), message_area);
+
+                    let [_, message_area, _] = Layout::horizontal([
+                        Constraint::Fill(1),
+                        Constraint::Length(40),
+                        Constraint::Fill(1)
+                    ]).areas(message_area);
+
+                    frame.render_widget(Text::from_iter([
+                        Line::from(""),
+                        Line::from("Table analysis is in progress").bold(),
+                        Line::from(""),
+                        Line::from("This operation may take some time."),
+                        Line::from("")
+                    ]), message_area);
+                }
+
                 Page::VacuumProgress => {
                     let [_, message_area, _] = Layout::vertical([
                         Constraint::Fill(1),
@@ -433,74 +1705,171 @@ pub fn run(mut terminal: Terminal<CrosstermBackend<Stdout>>, database: rusqlite:
             }
         })?;
 
-        loop {
-            let mut view = view.lock();
+        let mut view = view.lock();
 
-            if view.page == Page::VacuumProgress {
-                database.execute("VACUUM", [])?;
+        if let Some(running_analysis) = &analysis {
+            for update in running_analysis.updates.try_iter() {
+                view.apply(update);
+            }
+
+            if running_analysis.worker.is_finished() {
+                if let Some(finished_analysis) = analysis.take() {
+                    let (connection, schemas) = finished_analysis.finish()?;
 
-                view.page = Page::TablesChart;
-                view.tables = super::db_stats::query_structure(&database)?;
+                    database = Some(connection);
 
-                break;
+                    view.finish(schemas?);
+                }
+
+                continue;
             }
+        }
 
-            if event::poll(std::time::Duration::new(u64::MAX, 0))? {
-                #[allow(clippy::single_match)]
-                match event::read()? {
-                    Event::Key(key) => match key.code {
-                        KeyCode::Char('q') | KeyCode::Char('Q') |
-                        KeyCode::Char('й') | KeyCode::Char('Й') if view.page == Page::VacuumQuestion => view.page = Page::TablesChart,
+        if view.page == Page::VacuumProgress {
+            if let Some(connection) = database.take() {
+                connection.execute(&format!("VACUUM `{}`", view.schema().name), [])?;
 
-                        KeyCode::Char('q') | KeyCode::Char('Q') |
-                        KeyCode::Char('й') | KeyCode::Char('Й') => return Ok(()),
+                analysis = Some(Analysis::start(connection, sampling));
+            }
 
-                        KeyCode::Char('v') | KeyCode::Char('V') |
-                        KeyCode::Char('м') | KeyCode::Char('М') => view.page = Page::VacuumQuestion,
+            view.page = Page::TablesChart;
+            view.schemas.clear();
+            view.selected_schema = 0;
+            view.selected_table = 0;
+            view.progress = Some(Progress::default());
 
-                        KeyCode::Enter if view.page == Page::VacuumQuestion => view.page = Page::VacuumProgress,
+            continue;
+        }
 
-                        KeyCode::Left => {
-                            #[allow(clippy::implicit_saturating_sub)]
-                            if view.selected_table > 0 {
-                                view.selected_table -= 1;
-                            }
-                        }
+        if view.page == Page::AnalyzeProgress {
+            if let (Some(connection), Some((schema, table))) = (&database, view.analyze_target.take()) {
+                connection.execute(&format!("ANALYZE `{schema}`.`{table}`"), [])?;
 
-                        KeyCode::Right => {
-                            if view.selected_table + 1 < view.tables.len() {
-                                view.selected_table += 1;
-                            }
-                        }
+                // Only the statistics are reloaded as the rest
+                // of the table stays the same.
+                let statistics = db_stats::query_statistics(connection, &schema)?;
 
-                        KeyCode::Up => {
-                            if view.page == Page::TableDetails {
-                                view.page = Page::TablesChart;
-                            }
-                        }
+                if let Some(schema) = view.schemas.iter_mut().find(|item| item.name == schema) {
+                    for table in &mut schema.tables {
+                        statistics.apply(table);
+                    }
+                }
 
-                        KeyCode::Down => {
-                            if view.page == Page::TablesChart {
-                                view.page = Page::TableDetails;
-                            }
-                        }
+                // New statistics can change the query plans.
+                if let Some(workload) = &view.workload {
+                    view.workload = Some(db_stats::query_workload_replan(connection, workload)?);
+                }
+            }
 
-                        KeyCode::Enter => {
-                            match view.page {
-                                Page::TablesChart  => view.page = Page::TableDetails,
-                                Page::TableDetails => view.page = Page::TablesChart,
+            view.page = Page::TableDetails;
 
-                                _ => ()
-                            }
+            continue;
+        }
+
+        // Redraw analysis progress periodically.
+        let timeout = if analysis.is_some() {
+            std::time::Duration::from_millis(100)
+        } else {
+            std::time::Duration::new(u64::MAX, 0)
+        };
+
+        if event::poll(timeout)? {
+            #[allow(clippy::single_match)]
+            match event::read()? {
+                Event::Key(key) => match key.code {
+                    KeyCode::Char('q') | KeyCode::Char('Q') |
+                    KeyCode::Char('й') | KeyCode::Char('Й') if view.page == Page::VacuumQuestion => view.page = Page::TablesChart,
+
+                    KeyCode::Char('q') | KeyCode::Char('Q') |
+                    KeyCode::Char('й') | KeyCode::Char('Й') if view.page == Page::AnalyzeQuestion => {
+                        view.analyze_target = None;
+                        view.page = Page::TableDetails;
+                    }
+
+                    KeyCode::Enter if view.page == Page::VacuumQuestion => view.page = Page::VacuumProgress,
+                    KeyCode::Enter if view.page == Page::AnalyzeQuestion => view.page = Page::AnalyzeProgress,
+
+                    // Nothing but the answer is accepted while the user is asked.
+                    _ if matches!(view.page, Page::VacuumQuestion | Page::AnalyzeQuestion) => (),
+
+                    KeyCode::Char('q') | KeyCode::Char('Q') |
+                    KeyCode::Char('й') | KeyCode::Char('Й') => {
+                        if let Some(analysis) = analysis.take() {
+                            analysis.abort()?;
                         }
 
-                        _ => ()
+                        return Ok(());
+                    }
+
+                    // Schemas can be browsed only once their pages usage is read.
+                    _ if view.schemas.is_empty() => (),
+
+                    // Database is used by the analysis thread.
+                    KeyCode::Char('v') | KeyCode::Char('V') |
+                    KeyCode::Char('м') | KeyCode::Char('М') if analysis.is_none() => view.page = Page::VacuumQuestion,
+
+                    // Virtual tables can't be analyzed and the schema
+                    // could have no tables at all.
+                    KeyCode::Char('a') | KeyCode::Char('A') |
+                    KeyCode::Char('ф') | KeyCode::Char('Ф') if analysis.is_none() && view.tables().get(view.selected_table).is_some_and(|table| table.module.is_none()) => {
+                        view.analyze_target = Some((view.schema().name.clone(), view.table().name.clone()));
+                        view.page = Page::AnalyzeQuestion;
+                    }
+
+                    KeyCode::Char('m') | KeyCode::Char('M') |
+                    KeyCode::Char('ь') | KeyCode::Char('Ь') if view.page == Page::FileMap => view.page = Page::TablesChart,
+
+                    KeyCode::Char('m') | KeyCode::Char('M') |
+                    KeyCode::Char('ь') | KeyCode::Char('Ь') => view.page = Page::FileMap,
+
+                    KeyCode::Char('o') | KeyCode::Char('O') |
+                    KeyCode::Char('щ') | KeyCode::Char('Щ') if view.page == Page::Objects => view.page = Page::TablesChart,
+
+                    KeyCode::Char('o') | KeyCode::Char('O') |
+                    KeyCode::Char('щ') | KeyCode::Char('Щ') => view.page = Page::Objects,
+
+                    KeyCode::Char('w') | KeyCode::Char('W') |
+                    KeyCode::Char('ц') | KeyCode::Char('Ц') if view.page == Page::Workload => view.page = Page::TablesChart,
+
+                    KeyCode::Char('w') | KeyCode::Char('W') |
+                    KeyCode::Char('ц') | KeyCode::Char('Ц') => view.page = Page::Workload,
+
+                    KeyCode::Char('h') | KeyCode::Char('H') |
+                    KeyCode::Char('р') | KeyCode::Char('Р') if view.page == Page::Header => view.page = Page::TablesChart,
+
+                    KeyCode::Char('h') | KeyCode::Char('H') |
+                    KeyCode::Char('р') | KeyCode::Char('Р') => view.page = Page::Header,
+
+                    KeyCode::Left if view.selected_table > 0 => view.selected_table -= 1,
+                    KeyCode::Right if view.selected_table + 1 < view.tables().len() => view.selected_table += 1,
+
+                    KeyCode::Char('s') | KeyCode::Char('S') |
+                    KeyCode::Char('ы') | KeyCode::Char('Ы') => {
+                        view.show_system = !view.show_system;
+                        view.selected_table = 0;
+                    }
+
+                    KeyCode::Tab => {
+                        view.selected_schema = (view.selected_schema + 1) % view.schemas.len();
+                        view.selected_table = 0;
+                    }
+
+                    KeyCode::Up if view.page == Page::TableDetails => view.page = Page::TablesChart,
+                    KeyCode::Down if view.page == Page::TablesChart => view.page = Page::TableDetails,
+
+                    KeyCode::Enter => {
+                        match view.page {
+                            Page::TablesChart  => view.page = Page::TableDetails,
+                            Page::TableDetails => view.page = Page::TablesChart,
+
+                            _ => ()
+                        }
                     }
 
                     _ => ()
                 }
 
-                break;
+                _ => ()
             }
         }
     }